@@ -0,0 +1,216 @@
+//! Warns about files in the assets folder that are not referenced anywhere in
+//! the source code or the asset manifests, since they would be shipped but
+//! never loaded.
+//!
+//! With the `compressed` feature, it also packs the assets folder into a
+//! compressed archive that is embedded in the binary (see
+//! `assets::embedded::pack` for the format).
+//!
+//! Release builds also warn if there is no secret key for the protected save
+//! data (see `Protection`).
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+// Shared with `asset_key` so that patterns match the same assets
+#[path = "macros/src/glob.rs"]
+mod glob;
+
+use glob::glob_match;
+
+const ASSET_DIR: &str = "assets";
+const SOURCE_DIRS: [&str; 2] = ["src", "examples"];
+/// The attributes of `asset_key` that reference assets.
+const ASSET_ATTRS: [&str; 3] = ["asset", "asset_folder", "fallback"];
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", ASSET_DIR);
+    for dir in SOURCE_DIRS {
+        println!("cargo:rerun-if-changed={}", dir);
+    }
+
+    warn_unused_assets();
+    check_save_key();
+
+    #[cfg(feature = "compressed")]
+    pack_assets();
+}
+
+/// Prints a warning for every asset that is never referenced.
+fn warn_unused_assets() {
+    // The strings inside of the asset attributes of the source and every
+    // string in the asset manifests can be an asset path, a folder or a glob
+    // pattern
+    let manifests = files(Path::new(ASSET_DIR))
+        .into_iter()
+        .filter(|path| path.to_string_lossy().ends_with(".manifest.toml"))
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|manifest| string_literals(&manifest));
+    let literals: Vec<String> = SOURCE_DIRS
+        .iter()
+        .flat_map(|dir| files(Path::new(dir)))
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|source| attribute_literals(&source))
+        .chain(manifests)
+        .collect();
+
+    for asset in files(Path::new(ASSET_DIR)) {
+        let Ok(relative) = asset.strip_prefix(ASSET_DIR) else { continue };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        if relative.ends_with(".meta") {
+            continue;
+        }
+        let referenced = literals.iter().any(|l| {
+            *l == relative || relative.starts_with(&format!("{}/", l)) || glob_match(l, &relative)
+        });
+        if !referenced {
+            println!(
+                "cargo:warning=The asset '{}' is not referenced by any asset key",
+                relative
+            );
+        }
+    }
+}
+
+/// Returns the string literals inside of the asset attributes of a source file,
+/// like `#[asset = "..."]` or `#[fallback = "..."]`.
+fn attribute_literals(source: &str) -> Vec<String> {
+    let mut literals = vec![];
+    let mut rest = source;
+    while let Some(start) = rest.find("#[") {
+        rest = &rest[start + 2..];
+        let name = rest
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .next()
+            .unwrap_or_default();
+        if !ASSET_ATTRS.contains(&name) {
+            continue;
+        }
+
+        // The attribute ends at its closing bracket, but it can contain arrays
+        let mut depth = 1;
+        let end = rest
+            .char_indices()
+            .find_map(|(i, c)| {
+                match c {
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    _ => {},
+                }
+                (depth == 0).then_some(i)
+            })
+            .unwrap_or(rest.len());
+        literals.extend(string_literals(&rest[..end]));
+        rest = &rest[end..];
+    }
+    literals
+}
+
+/// Returns every string literal in some text.
+fn string_literals(text: &str) -> Vec<String> {
+    text.split('"')
+        .skip(1)
+        .step_by(2)
+        .map(String::from)
+        .collect()
+}
+
+/// Warns about release builds without `GAME_SAVE_KEY`, since the default key is
+/// public and anyone could edit protected saves with it.
+fn check_save_key() {
+    println!("cargo:rerun-if-env-changed=GAME_SAVE_KEY");
+    let release = std::env::var_os("CARGO_FEATURE_RELEASE").is_some();
+    if release && std::env::var_os("GAME_SAVE_KEY").is_none_or(|key| key.is_empty()) {
+        println!(
+            "cargo:warning=GAME_SAVE_KEY is not set, so protected saves use a public key and can be edited"
+        );
+    }
+}
+
+/// Compresses every file in the assets folder with deflate and writes them to
+/// `$OUT_DIR/assets.pack`, together with an index containing their paths,
+/// sizes and hashes.
+/// The build script also runs when the source changes, so the assets are only
+/// packed again if any of them changed since the last time.
+#[cfg(feature = "compressed")]
+fn pack_assets() {
+    use std::io::Write;
+
+    use flate2::{write::DeflateEncoder, Compression};
+
+    let mut assets = files(Path::new(ASSET_DIR));
+    assets.sort();
+
+    let out = PathBuf::from(std::env::var("OUT_DIR").expect("build script out dir"));
+    let stamp = assets_stamp(&assets);
+    if out.join("assets.pack").exists()
+        && fs::read_to_string(out.join("assets.stamp")).is_ok_and(|s| s == stamp)
+    {
+        return;
+    }
+
+    let mut index = vec![];
+    let mut blobs = vec![];
+    for asset in &assets {
+        let relative = asset
+            .strip_prefix(ASSET_DIR)
+            .expect("asset inside of the asset dir");
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let data = fs::read(asset).expect("readable asset");
+
+        let mut encoder = DeflateEncoder::new(vec![], Compression::best());
+        encoder.write_all(&data).expect("compressed asset");
+        let packed = encoder.finish().expect("compressed asset");
+
+        index.extend((relative.len() as u32).to_le_bytes());
+        index.extend(relative.as_bytes());
+        index.extend((blobs.len() as u64).to_le_bytes());
+        index.extend((packed.len() as u64).to_le_bytes());
+        index.extend((data.len() as u64).to_le_bytes());
+        index.extend(blake3::hash(&data).as_bytes());
+
+        blobs.extend(packed);
+    }
+
+    let mut pack = b"PACK".to_vec();
+    pack.extend(1u32.to_le_bytes());
+    pack.extend((assets.len() as u32).to_le_bytes());
+    pack.extend(index);
+    pack.extend(&blobs);
+
+    fs::write(out.join("assets.pack"), pack).expect("writable asset pack");
+    fs::write(out.join("assets.stamp"), stamp).expect("writable asset stamp");
+}
+
+/// Lists the path, size and modification time of every asset, to detect if
+/// they changed without reading them.
+#[cfg(feature = "compressed")]
+fn assets_stamp(assets: &[PathBuf]) -> String {
+    assets
+        .iter()
+        .map(|asset| {
+            let metadata = fs::metadata(asset).ok();
+            let size = metadata.as_ref().map(|m| m.len());
+            let modified = metadata.and_then(|m| m.modified().ok());
+            format!("{} {:?} {:?}\n", asset.display(), size, modified)
+        })
+        .collect()
+}
+
+/// Recursively lists all of the files inside of a directory.
+fn files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else { return vec![] };
+    entries
+        .flatten()
+        .flat_map(|entry| {
+            let path = entry.path();
+            if path.is_dir() {
+                files(&path)
+            } else {
+                vec![path]
+            }
+        })
+        .collect()
+}
@@ -35,7 +35,7 @@ struct Counter(u32);
 
 /// Event that is triggered when an entity collides with the screen border.
 #[derive(Event)]
-struct CollisionEvent;
+struct CollisionEvent(Vec2);
 
 // Systems
 // ---
@@ -118,7 +118,7 @@ fn update_velocity(
         // If there is a collision, create a new random color and send a collision event
         if collision {
             sprite.color = *rng.gen::<ColorWrapper>();
-            collision_writer.send(CollisionEvent);
+            collision_writer.send(CollisionEvent(trans.translation.truncate()));
         }
     }
 }
@@ -127,18 +127,17 @@ fn update_velocity(
 fn on_collision(
     mut cmd: Commands,
     mut counter: Query<(&mut Text2d, &mut Counter)>,
-    sound_assets: Res<AssetMap<SoundAssetKey>>,
     mut collision_reader: EventReader<CollisionEvent>,
 ) {
     let (mut text, mut counter) = single_mut!(counter);
 
-    for CollisionEvent in collision_reader.read() {
+    for CollisionEvent(position) in collision_reader.read() {
         counter.0 += 1;
         text.0 = counter.0.to_string();
 
-        cmd.spawn(AudioPlayer(
-            sound_assets.get(&SoundAssetKey::Boing).clone_weak(),
-        ));
+        // If the key has more than one variation, a different one is played each time
+        // Only a few copies can overlap, so many bounces at once don't get too loud
+        cmd.play_sfx(Sfx::new(SoundAssetKey::Boing).at(*position));
     }
 }
 
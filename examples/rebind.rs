@@ -0,0 +1,152 @@
+use game::prelude::*;
+
+fn main() {
+    App::new().add_plugins((GamePlugin, plugin)).run();
+}
+
+fn plugin(app: &mut App) {
+    app.init_resource::<Status>()
+        .add_systems(OnEnter(GameState::Play), init.run_if(run_once))
+        .add_systems(
+            Update,
+            (
+                start_rebind.in_set(PlaySet::Update),
+                on_rebind
+                    .in_set(PlaySet::ReadEvents)
+                    .run_if(on_event::<RebindEvent>),
+                update_text.in_set(PlaySet::Animation),
+            ),
+        );
+}
+
+/// The actions that can be rebound, with the key that starts rebinding them.
+const ACTIONS: [(KeyCode, Action); 2] = [
+    (KeyCode::Digit1, Action::Act),
+    (KeyCode::Digit2, Action::Pause),
+];
+
+// Resources
+// ---
+
+/// The result of the last rebind, and the input that can be moved from another
+/// action if it was already used.
+#[derive(Resource, Default)]
+struct Status {
+    message: String,
+    conflict: Option<(Action, Binding)>,
+}
+
+// Components
+// ---
+
+/// Marker for the text that lists the bindings.
+#[derive(Component)]
+struct BindingsText;
+
+// Systems
+// ---
+
+/// Spawn the text with the bindings.
+fn init(mut cmd: Commands, options: Res<GameOptions>, font_assets: Res<AssetMap<FontAssetKey>>) {
+    cmd.spawn((
+        Text::default(),
+        TextFont {
+            font: font_assets.get(&FontAssetKey::Main).clone_weak(),
+            font_size: 32.,
+            ..default()
+        },
+        TextColor(options.palette.light),
+        Node {
+            margin: UiRect::all(Val::Px(32.)),
+            ..default()
+        },
+        BindingsText,
+    ));
+}
+
+/// Start listening for an input when the key of an action is pressed, move a
+/// conflicting input with F and go back to the default bindings with R.
+fn start_rebind(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut capture: ResMut<InputCapture>,
+    mut bindings: ResMut<InputBindings>,
+    mut status: ResMut<Status>,
+) {
+    if capture.listening().is_some() {
+        return;
+    }
+    for (key, action) in ACTIONS {
+        if keys.just_pressed(key) {
+            capture.listen(action);
+            status.message = format!("Press any input for {:?}", action);
+            status.conflict = None;
+        }
+    }
+    if keys.just_pressed(KeyCode::KeyF) {
+        if let Some((action, binding)) = status.conflict.take() {
+            match bindings.replace(action, binding.clone()) {
+                Ok(_) => status.message = format!("Moved {:?} to {:?}", binding, action),
+                Err(other) => {
+                    status.message = format!("{:?} can't be moved from {:?}", binding, other)
+                },
+            }
+        }
+    }
+    if keys.just_pressed(KeyCode::KeyR) {
+        if let Err(e) = bindings.reset() {
+            error!("{:?}", e);
+        }
+        status.message = "Using the default bindings".into();
+        status.conflict = None;
+    }
+}
+
+/// Show the result of a rebind.
+fn on_rebind(mut events: EventReader<RebindEvent>, mut status: ResMut<Status>) {
+    for event in events.read() {
+        match event {
+            RebindEvent::Rebound { action, binding } => {
+                status.message = format!("{:?} is now on {:?}", action, binding);
+            },
+            RebindEvent::Conflict {
+                action,
+                binding,
+                other,
+            } => {
+                status.message = format!(
+                    "{:?} is already used by {:?}, press F to move it to {:?}",
+                    binding, other, action
+                );
+                status.conflict = Some((*action, binding.clone()));
+            },
+        }
+    }
+}
+
+/// List the bindings of each action and the last status.
+fn update_text(
+    bindings: Res<InputBindings>,
+    status: Res<Status>,
+    mut text: Query<&mut Text, With<BindingsText>>,
+) {
+    if !bindings.is_changed() && !status.is_changed() {
+        return;
+    }
+    let mut text = single_mut!(text);
+
+    let mut lines: Vec<_> = ACTIONS
+        .iter()
+        .map(|(key, action)| {
+            let inputs: Vec<_> = bindings
+                .get(*action)
+                .iter()
+                .map(|binding| format!("{:?}", binding))
+                .collect();
+            format!("[{:?}] {:?}: {}", key, action, inputs.join(", "))
+        })
+        .collect();
+    lines.push("[KeyR] Reset".into());
+    lines.push(String::new());
+    lines.push(status.message.clone());
+    text.0 = lines.join("\n");
+}
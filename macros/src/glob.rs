@@ -0,0 +1,21 @@
+//! Glob patterns for asset paths.
+//! This file is also included by the build script of the game, which can't
+//! depend on a proc macro crate, so it must not use any dependencies.
+
+/// Matches a path against a simple glob pattern, where `*` is any sequence of
+/// characters inside of a folder and `?` is any single character.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    match (pattern.chars().next(), path.chars().next()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            glob_match(&pattern[1..], path)
+                || path
+                    .chars()
+                    .next()
+                    .is_some_and(|c| c != '/' && glob_match(pattern, &path[c.len_utf8()..]))
+        },
+        (Some('?'), Some(c)) if c != '/' => glob_match(&pattern[1..], &path[c.len_utf8()..]),
+        (Some(p), Some(c)) if p == c => glob_match(&pattern[p.len_utf8()..], &path[c.len_utf8()..]),
+        _ => false,
+    }
+}
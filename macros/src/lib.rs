@@ -2,21 +2,33 @@
 
 #![warn(missing_docs)]
 
+use std::path::{Path, PathBuf};
+
+use glob::glob_match;
 use proc_macro as pm;
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{parse2, Data, DeriveInput, Meta};
+use syn::{parse2, Data, DeriveInput, Expr, ExprLit, Lit, LitBool, LitStr, Meta, Variant};
 
-/// Helper macro to allow deriving `asset` attributes for struct fields.
-/// There may be better ways to do this.
-#[proc_macro_derive(AssetAttr, attributes(asset))]
-pub fn derive_asset_attr(_item: pm::TokenStream) -> pm::TokenStream {
-    pm::TokenStream::new()
-}
+mod glob;
+
+/// Attributes that `asset_key` reads from each variant. They are removed from
+/// the output since they are not real attributes.
+const ASSET_ATTRS: [&str; 6] = [
+    "asset",
+    "asset_folder",
+    "fallback",
+    "weights",
+    "settings",
+    "sampler",
+];
 
 /// Defines an `AssetKey`, a collection of assets of the same type that are
 /// loaded together.
 ///
+/// Paths are checked at compile time. Folders and glob patterns are also
+/// expanded then, so adding new files to them only needs a rebuild.
+///
 /// # Examples
 ///
 /// ```ignore
@@ -30,46 +42,123 @@ pub fn derive_asset_attr(_item: pm::TokenStream) -> pm::TokenStream {
 /// pub enum SomeAssetKey {
 ///     #[asset = "some/asset.png"]
 ///     SomeVariant,
+///     // If this asset fails to load, the fallback is used instead
+///     #[asset = "some/other.png"]
+///     #[fallback = "some/asset.png"]
+///     OtherVariant,
+///     // Multiple assets can be loaded with a folder or a glob pattern
+///     #[asset_folder = "some/frames"]
+///     Frames,
+///     #[asset = "some/*.png"]
+///     Everything,
+///     // Or a list of paths, with optional weights for `AssetMap::pick`
+///     #[asset = ["some/a.ogg", "some/b.ogg"]]
+///     #[weights = [3, 1]]
+///     Variations,
+///     // Loader settings can be customized
+///     #[asset = "some/pixel.png"]
+///     #[sampler = "nearest"]
+///     Pixel,
+///     #[asset = "some/texture.png"]
+///     #[settings = |s: &mut ImageLoaderSettings| s.is_srgb = false]
+///     Texture,
 /// }
 /// ```
 #[proc_macro_attribute]
 pub fn asset_key(args: pm::TokenStream, input: pm::TokenStream) -> pm::TokenStream {
-    let input: TokenStream = input.into();
-    let DeriveInput { ident, data, .. } = parse2(input.clone()).unwrap();
+    let mut input: DeriveInput = parse2(input.into()).unwrap();
+    let ident = input.ident.clone();
 
-    let Data::Enum(data) = data else {
+    let Data::Enum(data) = &mut input.data else {
         panic!("An asset key must be an Enum");
     };
 
-    let names: Vec<_> = data
+    let mut errors = vec![];
+    let mut names = vec![];
+    let mut weights = vec![];
+
+    for v in data.variants.iter() {
+        let name = v.ident.clone();
+        let asset_paths = asset_paths(v).unwrap_or_else(|e| {
+            errors.push(e);
+            vec![]
+        });
+        let asset_weights = asset_weights(v, asset_paths.len()).unwrap_or_else(|e| {
+            errors.push(e);
+            None
+        });
+        let loads: Vec<_> = match asset_settings(v) {
+            Ok(Some(settings)) => asset_paths
+                .iter()
+                .map(|p| quote!(asset_server.load_with_settings(#p, #settings)))
+                .collect(),
+            Ok(None) => asset_paths
+                .iter()
+                .map(|p| quote!(asset_server.load(#p)))
+                .collect(),
+            Err(e) => {
+                errors.push(e);
+                vec![]
+            },
+        };
+        names.push(quote!((#ident::#name, vec![#(#loads),*])));
+        weights.push(match asset_weights {
+            Some(w) => quote!(#ident::#name => Some(&[#((#w) as f32),*])),
+            None => quote!(#ident::#name => None),
+        });
+    }
+
+    let fallbacks: Vec<_> = data
         .variants
         .iter()
         .map(|v| {
             let name = v.ident.clone();
-            let asset_path = v
-                .attrs
-                .iter()
-                .filter_map(|attr| {
-                    let Meta::NameValue(value) = attr.meta.clone() else { return None };
-                    if value.path.get_ident()? != "asset" {
-                        return None;
-                    };
-                    Some(value.value)
-                })
-                .next()
-                .expect("Each asset must provide a path attribute");
-            quote!((#ident::#name, asset_server.load(#asset_path)))
+            match attr_value(v, "fallback") {
+                Some(fallback_path) => quote!(#ident::#name => Some(#fallback_path)),
+                None => quote!(#ident::#name => None),
+            }
         })
         .collect();
 
+    // Check that every fallback exists at compile time
+    errors.extend(
+        data.variants
+            .iter()
+            .filter_map(|v| attr_value(v, "fallback"))
+            .filter_map(|path| check_path(&path).err()),
+    );
+    let errors: Vec<_> = errors.iter().map(|e| e.to_compile_error()).collect();
+
+    for v in data.variants.iter_mut() {
+        v.attrs.retain(|attr| {
+            !ASSET_ATTRS
+                .iter()
+                .any(|name| attr.path().is_ident(name))
+        });
+    }
+
     let args: TokenStream = args.into();
 
     let output = quote! {
-        #[derive(Copy, Clone, Eq, PartialEq, Hash, Reflect, macros::AssetAttr)]
+        #(#errors)*
+
+        #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Reflect)]
         #input
 
         impl AssetKey for #ident {
             type Asset = #args;
+
+            fn fallback(&self) -> Option<&'static str> {
+                match self {
+                    #(#fallbacks),*
+                }
+            }
+
+            fn weights(&self) -> Option<&'static [f32]> {
+                match self {
+                    #(#weights),*
+                }
+            }
         }
 
         impl FromWorld for AssetMap<#ident> {
@@ -81,3 +170,309 @@ pub fn asset_key(args: pm::TokenStream, input: pm::TokenStream) -> pm::TokenStre
     };
     output.into()
 }
+
+/// Resolves the paths of all of the assets of a variant, expanding folders and
+/// glob patterns. Each path is checked to exist in the `assets` folder of the
+/// crate that uses the macro at compile time.
+fn asset_paths(variant: &Variant) -> syn::Result<Vec<String>> {
+    if let Some(value) = attr_value(variant, "asset_folder") {
+        let folder = string_lit(&value)?;
+        let mut paths = list_files(&asset_root().join(folder.value()));
+        paths.sort();
+        if paths.is_empty() {
+            return Err(syn::Error::new(
+                folder.span(),
+                format!("The folder '{}' has no assets", folder.value()),
+            ));
+        }
+        return Ok(paths);
+    }
+
+    let Some(value) = attr_value(variant, "asset") else {
+        return Err(syn::Error::new_spanned(
+            variant,
+            "Each asset must provide an `asset` or `asset_folder` attribute",
+        ));
+    };
+    match value {
+        Expr::Array(array) => {
+            let paths = array
+                .elems
+                .iter()
+                .map(resolve_pattern)
+                .collect::<syn::Result<Vec<_>>>()?;
+            Ok(paths.concat())
+        },
+        value => resolve_pattern(&value),
+    }
+}
+
+/// Resolves a single asset path, or all of the paths that match it if it is a
+/// glob pattern.
+fn resolve_pattern(value: &Expr) -> syn::Result<Vec<String>> {
+    let path = string_lit(value)?;
+    if !path.value().contains(['*', '?']) {
+        check_path(value)?;
+        return Ok(vec![path.value()]);
+    }
+
+    let mut paths: Vec<_> = list_files(&asset_root())
+        .into_iter()
+        .filter(|p| glob_match(&path.value(), p))
+        .collect();
+    paths.sort();
+    if paths.is_empty() {
+        return Err(syn::Error::new(
+            path.span(),
+            format!("The pattern '{}' doesn't match any asset", path.value()),
+        ));
+    }
+    Ok(paths)
+}
+
+/// Reads the loader settings of a variant. They can be a closure that modifies
+/// the settings of the asset loader, `#[settings = |s: &mut Settings| ...]`, or
+/// a shorthand for common options like `#[sampler = "nearest"]` for images.
+fn asset_settings(variant: &Variant) -> syn::Result<Option<TokenStream>> {
+    let settings = attr_value(variant, "settings");
+    let sampler = attr_value(variant, "sampler");
+    match (settings, sampler) {
+        (Some(settings), None) => Ok(Some(quote!(#settings))),
+        (None, Some(sampler)) => {
+            let sampler = string_lit(&sampler)?;
+            let function = match sampler.value().as_str() {
+                "nearest" => quote!(nearest),
+                "linear" => quote!(linear),
+                other => {
+                    return Err(syn::Error::new(
+                        sampler.span(),
+                        format!("Unknown sampler '{}', use 'nearest' or 'linear'", other),
+                    ))
+                },
+            };
+            Ok(Some(quote!(
+                |s: &mut bevy::image::ImageLoaderSettings| {
+                    s.sampler = bevy::image::ImageSampler::#function();
+                }
+            )))
+        },
+        (Some(settings), Some(_)) => Err(syn::Error::new_spanned(
+            settings,
+            "Use either `settings` or `sampler`, but not both",
+        )),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Reads the `#[weights = [...]]` attribute of a variant, checking that there is
+/// one weight for each asset.
+fn asset_weights(variant: &Variant, count: usize) -> syn::Result<Option<Vec<Expr>>> {
+    let Some(value) = attr_value(variant, "weights") else { return Ok(None) };
+    let Expr::Array(array) = &value else {
+        return Err(syn::Error::new_spanned(
+            value,
+            "The weights must be an array of numbers",
+        ));
+    };
+    if array.elems.len() != count {
+        return Err(syn::Error::new_spanned(
+            array,
+            format!(
+                "There are {} weights but {} assets",
+                array.elems.len(),
+                count
+            ),
+        ));
+    }
+    Ok(Some(array.elems.iter().cloned().collect()))
+}
+
+/// Checks that an asset path is a string literal that points to an existing file
+/// inside of the `assets` folder of the crate that uses the macro.
+fn check_path(value: &Expr) -> syn::Result<()> {
+    let path = string_lit(value)?;
+    if !asset_root().join(path.value()).is_file() {
+        return Err(syn::Error::new(
+            path.span(),
+            format!("The asset '{}' doesn't exist in the assets folder", path.value()),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the string literal inside of an attribute value.
+fn string_lit(value: &Expr) -> syn::Result<&LitStr> {
+    let Expr::Lit(ExprLit {
+        lit: Lit::Str(path), ..
+    }) = value
+    else {
+        return Err(syn::Error::new_spanned(
+            value,
+            "The asset path must be a string literal",
+        ));
+    };
+    Ok(path)
+}
+
+/// The `assets` folder of the crate that is using the macro.
+fn asset_root() -> PathBuf {
+    Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default()).join("assets")
+}
+
+/// Recursively lists the files inside of a directory, returning their paths
+/// relative to the `assets` folder.
+fn list_files(dir: &Path) -> Vec<String> {
+    let root = asset_root();
+    let Ok(entries) = std::fs::read_dir(dir) else { return vec![] };
+    entries
+        .flatten()
+        .flat_map(|entry| {
+            let path = entry.path();
+            if path.is_dir() {
+                return list_files(&path);
+            }
+            let Ok(relative) = path.strip_prefix(&root) else { return vec![] };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            if relative.ends_with(".meta") {
+                return vec![];
+            }
+            vec![relative]
+        })
+        .collect()
+}
+
+/// Returns the value of a `#[name = value]` attribute of an enum variant.
+fn attr_value(variant: &Variant, name: &str) -> Option<Expr> {
+    variant.attrs.iter().find_map(|attr| {
+        let Meta::NameValue(value) = attr.meta.clone() else { return None };
+        if value.path.get_ident()? != name {
+            return None;
+        };
+        Some(value.value)
+    })
+}
+
+/// Implements `Persistent` for a resource, saving it in the data directory.
+/// The resource is loaded and added to the app automatically when the game
+/// starts.
+///
+/// All of the attributes are optional:
+/// - `path`: the name of the file, without the extension. Defaults to the name
+///   of the type.
+/// - `format`: a `Format`, like `Format::Ron`. Defaults to toml.
+/// - `policy`: the `SavePolicy` to use. Defaults to saving immediately.
+/// - `protection`: a `Protection` to detect changes made outside of the game,
+///   like `Protection::Signed`. Defaults to none.
+/// - `version` and `migrate`: the current version of the data and a function
+///   that upgrades older versions, see `Persistent::migrate`.
+/// - `backups`: how many previous versions to keep.
+/// - `register`: set it to `false` to add the resource manually with
+///   `PersistentExt::init_persistent`. Generic resources are never added
+///   automatically.
+///
+/// # Examples
+///
+/// ```ignore
+/// use game::prelude::*;
+///
+/// #[derive(Reflect, Resource, Serialize, Deserialize, Default, Persistent)]
+/// #[persistent(path = "settings", format = Format::Ron)]
+/// #[persistent(policy = SavePolicy::Debounced(1.))]
+/// pub struct SomeData {
+///     pub test: bool,
+/// }
+/// ```
+#[proc_macro_derive(Persistent, attributes(persistent))]
+pub fn derive_persistent(input: pm::TokenStream) -> pm::TokenStream {
+    let input: DeriveInput = parse2(input.into()).unwrap();
+    let ident = &input.ident;
+
+    let mut path = LitStr::new(&ident.to_string(), ident.span());
+    let mut items = vec![];
+    let mut migrate = None;
+    let mut version = None;
+    let mut register = input.generics.params.is_empty();
+
+    let result = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("persistent"))
+        .try_for_each(|attr| {
+            attr.parse_nested_meta(|meta| {
+                let value = meta.value()?;
+                let key = meta.path.get_ident().map(|i| i.to_string()).unwrap_or_default();
+                match key.as_str() {
+                    "path" => path = value.parse()?,
+                    "register" => register = value.parse::<LitBool>()?.value,
+                    "format" => {
+                        let format: Expr = value.parse()?;
+                        items.push(quote!(const FORMAT: Format = #format;));
+                    },
+                    "policy" => {
+                        let policy: Expr = value.parse()?;
+                        items.push(quote!(const POLICY: SavePolicy = #policy;));
+                    },
+                    "protection" => {
+                        let protection: Expr = value.parse()?;
+                        items.push(quote!(const PROTECTION: Protection = #protection;));
+                    },
+                    "backups" => {
+                        let backups: Expr = value.parse()?;
+                        items.push(quote!(const BACKUPS: usize = #backups;));
+                    },
+                    "version" => {
+                        let v: Expr = value.parse()?;
+                        version = Some(v.clone());
+                        items.push(quote!(const VERSION: u32 = #v;));
+                    },
+                    "migrate" => {
+                        let function: syn::Path = value.parse()?;
+                        items.push(quote!(
+                            fn migrate(version: u32, data: toml::Value) -> Result<toml::Value> {
+                                #function(version, data)
+                            }
+                        ));
+                        migrate = Some(function);
+                    },
+                    _ => {
+                        return Err(meta.error(
+                            "Unknown attribute, expected `path`, `format`, `policy`, `protection`, `backups`, `version`, `migrate` or `register`",
+                        ))
+                    },
+                }
+                Ok(())
+            })
+        })
+        .and_then(|_| match (&version, &migrate) {
+            (Some(version), None) => Err(syn::Error::new_spanned(
+                version,
+                "A `migrate` function is needed to upgrade older versions",
+            )),
+            _ => Ok(()),
+        });
+    if let Err(e) = result {
+        return e.to_compile_error().into();
+    }
+
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let registration = register.then(|| {
+        quote! {
+            inventory::submit! {
+                PersistentRegistration::new::<#ident>()
+            }
+        }
+    });
+    let output = quote! {
+        impl #impl_generics Persistent for #ident #type_generics #where_clause {
+            #(#items)*
+
+            #[inline]
+            fn path() -> &'static str {
+                #path
+            }
+        }
+
+        #registration
+    };
+    output.into()
+}
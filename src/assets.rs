@@ -2,45 +2,79 @@
 //! provides helpful functions for handling assets, loading them automatically
 //! and making them easily available.
 
-use std::{
-    any::TypeId,
-    sync::{LazyLock, Mutex},
-};
+use std::{any::TypeId, sync::Mutex};
 
-use bevy::reflect::{GetTypeRegistration, ReflectFromPtr, Typed};
+use bevy::{
+    asset::{LoadState, RecursiveDependencyLoadState},
+    reflect::{GetTypeRegistration, ReflectFromPtr, Typed},
+    state::state::FreelyMutableState,
+};
+use rand::{seq::SliceRandom, Rng};
 
 use crate::prelude::*;
 
-//#[cfg(feature = "embedded")]
-// pub mod embedded;
+#[cfg(feature = "embedded")]
+pub mod embedded;
 pub mod fonts;
+pub mod layers;
+pub mod manifest;
 pub mod meta;
 pub mod music;
 pub mod sound;
 
-/// Keeps track of all of the registered asset collections that have not yet
-/// been loaded. Used to query the loading state of the assets and transition
-/// into the next game state.
-static ASSET_MAP: LazyLock<Mutex<Vec<TypeId>>> = LazyLock::new(|| Mutex::new(vec![]));
-
 pub(super) fn plugin(app: &mut App) {
-    app.add_plugins((fonts::plugin, meta::plugin, music::plugin, sound::plugin))
-        .add_systems(Update, check_loaded.run_if(in_state(GameState::Startup)));
+    app.init_state::<LoadingState>()
+        .enable_state_scoped_entities::<LoadingState>()
+        .init_resource::<LoadingProgress>()
+        .init_resource::<PendingAssets>()
+        .add_plugins((
+            fonts::plugin,
+            manifest::plugin,
+            meta::plugin,
+            music::plugin,
+            sound::plugin,
+        ))
+        .add_systems(
+            Update,
+            check_loaded
+                .run_if(in_state(LoadingState::Loading))
+                .run_if(not(in_state(GameState::Error))),
+        );
 }
 
 /// The prelude of this module.
 pub mod prelude {
     pub use super::{
         fonts::FontAssetKey,
+        layers::AssetLayers,
+        manifest::{AssetManifest, ManifestExt, ManifestKey},
         meta::MetaAssetKey,
         music::MusicAssetKey,
         sound::SoundAssetKey,
+        AssetErrors,
         AssetExt,
         AssetKey,
         AssetMap,
+        LoadingProgress,
+        LoadingState,
+        Progress,
     };
 }
 
+// States
+// ---
+
+/// Tracks if there are asset collections still being loaded. It runs
+/// alongside `GameState`, and while it is `Loading` a loading screen is shown.
+#[derive(Default, States, Std!)]
+pub enum LoadingState {
+    /// Some asset collections have not finished loading yet.
+    #[default]
+    Loading,
+    /// Every registered asset collection is loaded.
+    Done,
+}
+
 // Resources
 // ---
 
@@ -61,46 +95,214 @@ pub mod prelude {
 /// fn system(some_assets: Res<AssetMap<SomeAssetKey>>) {
 ///     let asset = some_assets.get(&SomeAssetKey::SomeAsset).clone_weak();
 /// }
+///
+/// // Keys can also hold a list of assets from a folder or a glob pattern
+/// #[asset_key(Image)]
+/// pub enum FramesAssetKey {
+///     #[asset_folder = "some/frames"]
+///     Walk,
+/// }
+///
+/// fn animate(frames: Res<AssetMap<FramesAssetKey>>) {
+///     let walk = frames.get_all(&FramesAssetKey::Walk);
+/// }
 /// ```
 #[derive(Resource, Reflect, Deref, DerefMut)]
 #[reflect(AssetsLoaded)]
-pub struct AssetMap<K: AssetKey>(HashMap<K, Handle<K::Asset>>);
+pub struct AssetMap<K: AssetKey>(
+    #[deref] HashMap<K, Vec<Handle<K::Asset>>>,
+    /// The index of the last asset returned by `pick` for each key.
+    #[reflect(ignore)]
+    Mutex<HashMap<K, usize>>,
+);
+
+/// Keeps track of all of the registered asset collections that have not yet
+/// been loaded. Used to query the loading state of the assets and transition
+/// into the next game state.
+#[derive(Resource, Default, Clone, Deref, DerefMut)]
+struct PendingAssets(Vec<TypeId>);
+
+/// Descriptions of the assets that failed to load and had no fallback. When
+/// this happens the game goes into `GameState::Error`.
+#[derive(Resource, Default, Debug, Deref)]
+pub struct AssetErrors(pub Vec<String>);
+
+/// Keeps track of how many assets have been loaded for each `AssetKey` type.
+///
+/// # Examples
+///
+/// ```
+/// use game::prelude::*;
+///
+/// fn system(progress: Res<LoadingProgress>) {
+///     let fonts = progress.get::<FontAssetKey>().unwrap_or_default();
+///     info!("fonts: {}/{}", fonts.loaded, fonts.total);
+///     info!("overall: {:.0}%", progress.total().fraction() * 100.);
+/// }
+/// ```
+#[derive(Resource, Default, Debug)]
+pub struct LoadingProgress(HashMap<TypeId, Progress>);
+
+impl LoadingProgress {
+    /// Returns the progress of the collection for an `AssetKey` type, if it
+    /// has been registered.
+    pub fn get<K: AssetKey>(&self) -> Option<Progress> {
+        self.0.get(&TypeId::of::<AssetMap<K>>()).copied()
+    }
+
+    /// Returns the combined progress of all of the registered collections.
+    pub fn total(&self) -> Progress {
+        self.0
+            .values()
+            .fold(Progress::default(), |acc, p| Progress {
+                loaded: acc.loaded + p.loaded,
+                total: acc.total + p.total,
+            })
+    }
+}
+
+/// Number of loaded assets out of the total of a collection.
+#[derive(Default, Debug, Copy!, Eq!)]
+pub struct Progress {
+    /// Assets that finished loading, including their dependencies.
+    pub loaded: usize,
+    /// Number of assets in the collection.
+    pub total: usize,
+}
+
+impl Progress {
+    /// Returns the loaded fraction between 0 and 1. Empty collections count as
+    /// fully loaded.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 1.;
+        }
+        self.loaded as f32 / self.total as f32
+    }
+
+    /// Checks if every asset is loaded.
+    pub fn is_done(&self) -> bool {
+        self.loaded >= self.total
+    }
+}
 
 /// Represents a handle to a collection of assets of a certain type type.
 pub trait AssetKey:
-    Sized + Eq + std::hash::Hash + Reflect + FromReflect + TypePath + GetTypeRegistration
+    Sized
+    + Clone
+    + Eq
+    + std::hash::Hash
+    + std::fmt::Debug
+    + Reflect
+    + FromReflect
+    + TypePath
+    + GetTypeRegistration
 {
     /// The type of the assets in this collection.
     type Asset: Asset;
+
+    /// Path of the asset to use if this one fails to load.
+    fn fallback(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Relative probability of each asset of this key being chosen by `pick`.
+    fn weights(&self) -> Option<&'static [f32]> {
+        None
+    }
 }
 
 impl<K: AssetKey, T> From<T> for AssetMap<K>
 where
-    T: Into<HashMap<K, Handle<K::Asset>>>,
+    T: Into<HashMap<K, Vec<Handle<K::Asset>>>>,
 {
     fn from(value: T) -> Self {
-        Self(value.into())
+        Self(value.into(), default())
     }
 }
 
 impl<K: AssetKey> AssetMap<K> {
-    /// Returns a weak clone of the asset handle.
+    /// Returns a weak clone of the asset handle. If the key has more than one
+    /// asset, the first one is returned.
     pub fn get(&self, key: &K) -> Handle<K::Asset> {
-        self[key].clone_weak()
+        self[key][0].clone_weak()
+    }
+
+    /// Returns weak clones of all of the asset handles of a key, for example,
+    /// the ones loaded from an `asset_folder`.
+    pub fn get_all(&self, key: &K) -> Vec<Handle<K::Asset>> {
+        self[key].iter().map(|h| h.clone_weak()).collect()
+    }
+
+    /// Returns a weak clone of a random asset handle of a key, taking into
+    /// account its `weights`. The same asset is not returned twice in a row
+    /// unless it is the only one.
+    pub fn pick(&self, key: &K, rng: &mut impl Rng) -> Handle<K::Asset> {
+        let handles = &self[key];
+        let mut last = self.1.lock().unwrap();
+        let candidates: Vec<_> = (0..handles.len())
+            .filter(|i| handles.len() == 1 || last.get(key) != Some(i))
+            .collect();
+        let weight = |i: &usize| key.weights().map_or(1., |w| w[*i]);
+        let index = *candidates
+            .choose_weighted(rng, weight)
+            .unwrap_or(&candidates[0]);
+        last.insert(key.clone(), index);
+        handles[index].clone_weak()
     }
 }
 
+/// Holds a transition into a state until the asset collections registered for
+/// it with `load_asset_in_state` are loaded.
+#[derive(Resource)]
+struct PendingState<S: States>(S);
+
 /// Local trait to query the loading state of all of the asset maps.
 #[reflect_trait]
 trait AssetsLoaded {
-    /// Check if all of the assets are loaded.
-    fn all_loaded(&self, asset_server: &AssetServer) -> bool;
+    /// Count how many of the assets are loaded.
+    fn progress(&self, asset_server: &AssetServer) -> Progress;
+    /// Replace the assets that failed to load with their fallbacks. Returns a
+    /// description of the failed assets that don't have one.
+    fn handle_failed(&mut self, asset_server: &AssetServer) -> Vec<String>;
 }
 
 impl<K: AssetKey> AssetsLoaded for AssetMap<K> {
-    fn all_loaded(&self, asset_server: &AssetServer) -> bool {
-        self.values()
-            .all(|x| asset_server.is_loaded_with_dependencies(x))
+    fn progress(&self, asset_server: &AssetServer) -> Progress {
+        Progress {
+            loaded: self
+                .values()
+                .flatten()
+                .filter(|x| asset_server.is_loaded_with_dependencies(*x))
+                .count(),
+            total: self.values().map(|v| v.len()).sum(),
+        }
+    }
+
+    fn handle_failed(&mut self, asset_server: &AssetServer) -> Vec<String> {
+        let mut errors = vec![];
+        for (key, handle) in self
+            .iter_mut()
+            .flat_map(|(key, handles)| handles.iter_mut().map(move |h| (key, h)))
+        {
+            let err = match asset_server.get_load_states(handle.id()) {
+                Some((LoadState::Failed(err), ..)) => err,
+                Some((.., RecursiveDependencyLoadState::Failed(err))) => err,
+                _ => continue,
+            };
+            let path = handle.path().map(|p| p.to_string()).unwrap_or_default();
+            match key.fallback() {
+                Some(fallback) if path != fallback => {
+                    warn!(
+                        "Failed to load {:?} ('{}'), using fallback '{}': {}",
+                        key, path, fallback, err
+                    );
+                    *handle = asset_server.load(fallback);
+                },
+                _ => errors.push(format!("{:?} ('{}'): {}", key, path, err)),
+            }
+        }
+        errors
     }
 }
 
@@ -123,12 +325,27 @@ impl<K: AssetKey> AssetsLoaded for AssetMap<K> {
 ///     #[asset = "some/asset.png"]
 ///     SomeVariant,
 /// }
+///
+/// // Or only while a state is active, unloading it afterwards
+/// pub fn level_plugin(app: &mut App) {
+///     app.load_asset_in_state::<SomeAssetKey>(GameState::Play);
+/// }
 /// ```
 pub trait AssetExt {
     /// Loads an asset key.
     fn load_asset<K: AssetKey + Typed>(&mut self) -> &mut Self
     where
         AssetMap<K>: FromWorld;
+
+    /// Loads an asset key when entering a state and drops it when exiting.
+    /// The transition into the state is held back until all of the assets are
+    /// loaded, so its `OnEnter` systems can already use them.
+    fn load_asset_in_state<K: AssetKey + Typed, S: FreelyMutableState>(
+        &mut self,
+        state: S,
+    ) -> &mut Self
+    where
+        AssetMap<K>: FromWorld;
 }
 
 impl AssetExt for App {
@@ -136,49 +353,151 @@ impl AssetExt for App {
     where
         AssetMap<K>: FromWorld,
     {
-        ASSET_MAP.lock().unwrap().push(TypeId::of::<AssetMap<K>>());
+        self.world_mut()
+            .get_resource_or_init::<PendingAssets>()
+            .push(TypeId::of::<AssetMap<K>>());
         self.init_resource::<AssetMap<K>>()
             .register_type::<AssetMap<K>>()
     }
+
+    fn load_asset_in_state<K: AssetKey + Typed, S: FreelyMutableState>(
+        &mut self,
+        state: S,
+    ) -> &mut Self
+    where
+        AssetMap<K>: FromWorld,
+    {
+        let target = state.clone();
+        self.register_type::<AssetMap<K>>()
+            .add_systems(PreUpdate, move |world: &mut World| {
+                hold_transition::<K, S>(world, &target)
+            })
+            .add_systems(
+                Update,
+                release_transition::<S>
+                    .run_if(in_state(LoadingState::Done))
+                    .run_if(resource_exists::<PendingState<S>>),
+            )
+            .add_systems(OnExit(state), unload_asset::<K>)
+    }
+}
+
+/// When a transition into `state` is requested and its asset collection is not
+/// present, it starts loading it and holds the transition in `PendingState`.
+fn hold_transition<K: AssetKey, S: FreelyMutableState>(world: &mut World, state: &S)
+where
+    AssetMap<K>: FromWorld,
+{
+    let target = match world.resource::<NextState<S>>() {
+        NextState::Pending(next) => next,
+        NextState::Unchanged => match world.get_resource::<PendingState<S>>() {
+            Some(pending) => &pending.0,
+            None => return,
+        },
+    };
+    if target != state || world.contains_resource::<AssetMap<K>>() {
+        return;
+    }
+
+    world
+        .resource_mut::<PendingAssets>()
+        .push(TypeId::of::<AssetMap<K>>());
+    world.init_resource::<AssetMap<K>>();
+    world.insert_resource(PendingState(state.clone()));
+    world.resource_mut::<NextState<S>>().reset();
+    world
+        .resource_mut::<NextState<LoadingState>>()
+        .set(LoadingState::Loading);
 }
 
-/// Checks the elements of `ASSET_MAP` to check if they are loaded, and if they
-/// are, removes them from it. When there are no resources left to load,
-/// progress into the next `GameState`.
+/// Once everything is loaded, continues with the held state transition.
+fn release_transition<S: FreelyMutableState>(
+    mut cmd: Commands,
+    pending: Res<PendingState<S>>,
+    mut next_state: ResMut<NextState<S>>,
+) {
+    next_state.set(pending.0.clone());
+    cmd.remove_resource::<PendingState<S>>();
+}
+
+/// Drops the asset collection when exiting its state, releasing the strong
+/// handles so the assets can be freed.
+fn unload_asset<K: AssetKey>(world: &mut World) {
+    let id = TypeId::of::<AssetMap<K>>();
+    world.resource_mut::<PendingAssets>().retain(|x| *x != id);
+    world.resource_mut::<LoadingProgress>().0.remove(&id);
+    world.remove_resource::<AssetMap<K>>();
+}
+
+/// Checks the elements of `PendingAssets` to check if they are loaded, and if
+/// they are, removes them from it. The `LoadingProgress` is updated along the
+/// way. If any asset fails to load without a fallback, go to
+/// `GameState::Error`. When there are no resources left to load, progress into
+/// the next `LoadingState` and `GameState`.
 fn check_loaded(world: &mut World) {
-    let mut map = ASSET_MAP.lock().unwrap();
+    let pending = world.resource::<PendingAssets>().clone();
     let mut loaded = vec![];
+    let mut progress = vec![];
+    let mut errors = vec![];
 
-    for id in map.iter() {
-        match is_resource_loaded(*id, world) {
-            Ok(l) if !l => continue,
+    for id in pending.iter() {
+        match check_resource(*id, world) {
+            Ok((p, e)) => {
+                progress.push((*id, p));
+                errors.extend(e);
+                if !p.is_done() {
+                    continue;
+                }
+            },
             Err(e) => warn!("{}", e),
-            _ => {},
         }
         loaded.push(*id);
     }
 
-    (*map).retain(|x| !loaded.contains(x));
+    let mut map = world.resource_mut::<PendingAssets>();
+    map.retain(|x| !loaded.contains(x));
+    let done = map.is_empty();
+
+    let mut loading = world.resource_mut::<LoadingProgress>();
+    loading.0.extend(progress);
+
+    if !errors.is_empty() {
+        for e in &errors {
+            error!("Failed to load asset {}", e);
+        }
+        world.insert_resource(AssetErrors(errors));
+        world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Error);
+        return;
+    }
 
-    if map.len() == 0 {
-        let mut next_state = world
-            .get_resource_mut::<NextState<GameState>>()
-            .expect("NextState should exist");
-        // TODO: Rework menu
-        next_state.set(GameState::Play);
+    if done {
+        world
+            .resource_mut::<NextState<LoadingState>>()
+            .set(LoadingState::Done);
+        if *world.resource::<State<GameState>>() == GameState::Startup {
+            // TODO: Rework menu
+            world
+                .resource_mut::<NextState<GameState>>()
+                .set(GameState::Play);
+        }
     }
 }
 
-/// Checks if an `AssetMap` has finished loading. It has to use some quirky
-/// reflection tricks since each asset map is a different type.
-fn is_resource_loaded(id: TypeId, world: &World) -> Result<bool> {
+/// Handles failed assets and checks how many assets of an `AssetMap` have
+/// finished loading. It has to use some quirky reflection tricks since each
+/// asset map is a different type.
+fn check_resource(id: TypeId, world: &mut World) -> Result<(Progress, Vec<String>)> {
     // Get world resources
     let asset_server = world
         .get_resource::<AssetServer>()
-        .expect("Bevy's asset server should exist");
+        .expect("Bevy's asset server should exist")
+        .clone();
     let registry = world
         .get_resource::<AppTypeRegistry>()
-        .expect("Bevy's type registry should exist");
+        .expect("Bevy's type registry should exist")
+        .clone();
     let registry = registry.read();
 
     // Get the AssetMap component id and raw pointer
@@ -188,7 +507,7 @@ fn is_resource_loaded(id: TypeId, world: &World) -> Result<bool> {
         .get_resource_id(registration.type_id())
         .context("Couldn't get the component id of the resource")?;
     let ptr = world
-        .get_resource_by_id(component_id)
+        .get_resource_mut_by_id(component_id)
         .context("The resource is not registred")?;
 
     // Convert the pointer into a Reflect trait
@@ -198,7 +517,7 @@ fn is_resource_loaded(id: TypeId, world: &World) -> Result<bool> {
         .context("Type registration should exist")?;
     // SAFETY: from the context it is known that `ReflectFromPtr` was made for the
     // type of the `MutUntyped`
-    let resource: &dyn Reflect = unsafe { reflect_from_ptr.as_reflect(ptr) };
+    let resource: &mut dyn Reflect = unsafe { reflect_from_ptr.as_reflect_mut(ptr.into_inner()) };
 
     // Get the LoadedAsset trait registration
     let loaded_trait = registry
@@ -207,9 +526,212 @@ fn is_resource_loaded(id: TypeId, world: &World) -> Result<bool> {
 
     // Convert the AssetMap dyn Reflect object into a dyn LoadedAsset trait
     let resource = loaded_trait
-        .get(resource)
+        .get_mut(resource)
         .context("The resource doesn't implement the LoadedAsset trait")?;
 
-    // Check if all of the assets are loaded
-    Ok(resource.all_loaded(asset_server))
+    // Replace the failed assets and count the ones that are loaded
+    let errors = resource.handle_failed(&asset_server);
+    Ok((resource.progress(&asset_server), errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{
+        asset::{io::Reader, AssetLoader, LoadContext},
+        state::app::StatesPlugin,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    /// An asset that ignores the contents of the file, to load assets without
+    /// the rest of the engine.
+    #[derive(Asset, TypePath)]
+    struct TestAsset;
+
+    #[derive(Default)]
+    struct TestLoader;
+
+    impl AssetLoader for TestLoader {
+        type Asset = TestAsset;
+        type Error = anyhow::Error;
+        type Settings = ();
+
+        async fn load(
+            &self,
+            reader: &mut dyn Reader,
+            _settings: &(),
+            _load_context: &mut LoadContext<'_>,
+        ) -> Result<TestAsset> {
+            reader.read_to_end(&mut vec![]).await?;
+            Ok(TestAsset)
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["ttf", "ogg"]
+        }
+    }
+
+    #[asset_key(TestAsset)]
+    enum TestAssetKey {
+        #[asset = "fonts/sans.ttf"]
+        Sans,
+        #[asset = ["fonts/sans.ttf", "fonts/pixel.ttf", "sound/boing.ogg"]]
+        #[weights = [10, 1, 1]]
+        Variations,
+    }
+
+    /// A collection with assets that don't exist. It can't use `asset_key`,
+    /// since it checks that the files exist.
+    #[derive(Reflect, Std!)]
+    enum MissingAssetKey {
+        WithFallback,
+        WithoutFallback,
+    }
+
+    impl AssetKey for MissingAssetKey {
+        type Asset = TestAsset;
+
+        fn fallback(&self) -> Option<&'static str> {
+            match self {
+                MissingAssetKey::WithFallback => Some("fonts/sans.ttf"),
+                MissingAssetKey::WithoutFallback => None,
+            }
+        }
+    }
+
+    /// Loads a missing asset for a key.
+    fn load_missing(app: &mut App, key: MissingAssetKey) {
+        let handle = app.world().resource::<AssetServer>().load("missing.ttf");
+        app.insert_resource(AssetMap::from([(key, vec![handle])]))
+            .register_type::<AssetMap<MissingAssetKey>>();
+        app.world_mut()
+            .resource_mut::<PendingAssets>()
+            .push(TypeId::of::<AssetMap<MissingAssetKey>>());
+    }
+
+    /// Creates an app with the asset loading systems but without any of the
+    /// collections of the game.
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), StatesPlugin))
+            .init_state::<GameState>()
+            .init_state::<LoadingState>()
+            .init_resource::<LoadingProgress>()
+            .init_resource::<PendingAssets>()
+            .init_asset::<TestAsset>()
+            .init_asset_loader::<TestLoader>()
+            .add_systems(
+                Update,
+                check_loaded
+                    .run_if(in_state(LoadingState::Loading))
+                    .run_if(not(in_state(GameState::Error))),
+            );
+        app
+    }
+
+    /// Updates the app until the condition is met or it runs out of tries.
+    fn update_until(app: &mut App, condition: impl Fn(&World) -> bool) -> bool {
+        for _ in 0..500 {
+            app.update();
+            if condition(app.world()) {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    fn game_state(world: &World) -> GameState {
+        *world.resource::<State<GameState>>().get()
+    }
+
+    #[test]
+    fn loads_assets_in_state() {
+        let mut app = app();
+        app.load_asset_in_state::<TestAssetKey, _>(GameState::Play);
+
+        // Nothing else is loading, so it tries to go to `Play` right away
+        app.update();
+        assert!(!app.world().contains_resource::<AssetMap<TestAssetKey>>());
+
+        // The transition is held until the collection is loaded
+        app.update();
+        assert!(app.world().contains_resource::<AssetMap<TestAssetKey>>());
+        assert_eq!(game_state(app.world()), GameState::Startup);
+        assert!(update_until(&mut app, |world| {
+            let loaded = world
+                .resource::<LoadingProgress>()
+                .get::<TestAssetKey>()
+                .is_some_and(|p| p.is_done());
+            let play = game_state(world) == GameState::Play;
+            assert!(loaded || !play, "Entered the state before loading");
+            play
+        }));
+
+        // The collection is dropped when exiting the state
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::End);
+        app.update();
+        assert!(!app.world().contains_resource::<AssetMap<TestAssetKey>>());
+    }
+
+    #[test]
+    fn uses_the_fallback() {
+        let mut app = app();
+        load_missing(&mut app, MissingAssetKey::WithFallback);
+
+        assert!(update_until(&mut app, |world| {
+            *world.resource::<State<LoadingState>>() == LoadingState::Done
+        }));
+        let map = app.world().resource::<AssetMap<MissingAssetKey>>();
+        let handle = map.get(&MissingAssetKey::WithFallback);
+        assert_eq!(handle.path().unwrap().to_string(), "fonts/sans.ttf");
+        assert!(!app.world().contains_resource::<AssetErrors>());
+        assert_eq!(game_state(app.world()), GameState::Play);
+    }
+
+    #[test]
+    fn fails_without_a_fallback() {
+        let mut app = app();
+        load_missing(&mut app, MissingAssetKey::WithoutFallback);
+
+        assert!(update_until(&mut app, |world| {
+            game_state(world) == GameState::Error
+        }));
+        let errors = app.world().resource::<AssetErrors>();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("missing.ttf"));
+        assert_eq!(
+            *app.world().resource::<State<LoadingState>>(),
+            LoadingState::Loading
+        );
+    }
+
+    #[test]
+    fn picks_weighted_variations() {
+        let mut app = app();
+        app.init_resource::<AssetMap<TestAssetKey>>();
+        let map = app.world().resource::<AssetMap<TestAssetKey>>();
+        let handles = map.get_all(&TestAssetKey::Variations);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut counts = [0; 3];
+        let mut last = None;
+        for _ in 0..1000 {
+            let handle = map.pick(&TestAssetKey::Variations, &mut rng);
+            let index = handles.iter().position(|h| *h == handle).unwrap();
+            assert_ne!(last, Some(index), "Picked the same asset twice in a row");
+            last = Some(index);
+            counts[index] += 1;
+        }
+
+        // Without repeats the first asset can be picked at most half of the
+        // time, but it is still picked more than the others
+        assert!(counts[0] * 2 > counts[1] * 3);
+        assert!(counts[0] * 2 > counts[2] * 3);
+    }
 }
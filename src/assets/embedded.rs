@@ -0,0 +1,366 @@
+//! Simplified version of <https://github.com/vleue/bevy_embedded_assets>
+//! Embeds the asset folder inside of the binary so that it can be distributed
+//! as a single file.
+//! With the `compressed` feature, the assets are packed into a compressed
+//! archive instead, which is decompressed and verified when reading each file.
+
+use std::{
+    borrow::Cow,
+    io::SeekFrom,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::Poll,
+};
+
+use bevy::{
+    asset::io::{AssetReader, AssetReaderError, AsyncSeekForward, PathStream, Reader},
+    tasks::futures_lite::{AsyncRead, AsyncSeek, Stream},
+};
+#[cfg(not(feature = "compressed"))]
+use include_dir::{include_dir, Dir};
+
+use crate::prelude::*;
+
+#[cfg(feature = "compressed")]
+mod pack;
+
+/// Every file in the asset folder, included at compile time.
+#[cfg(not(feature = "compressed"))]
+static ASSET_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/assets");
+
+/// A file embedded in the binary.
+#[cfg(not(feature = "compressed"))]
+type EmbeddedFile = &'static [u8];
+
+/// A compressed file embedded in the binary.
+#[cfg(feature = "compressed")]
+type EmbeddedFile = pack::PackedFile;
+
+/// A wrapper around the raw bytes of an asset
+#[derive(Clone)]
+pub struct DataReader {
+    data: Cow<'static, [u8]>,
+    pos: usize,
+}
+
+impl DataReader {
+    /// Creates a reader positioned at the start of the data.
+    pub fn new(data: Cow<'static, [u8]>) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Moves the position of the reader, making sure that it doesn't go before
+    /// the start of the data.
+    fn seek_to(&mut self, pos: i64) -> std::io::Result<u64> {
+        let pos = u64::try_from(pos).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Seek before the start of the data",
+            )
+        })?;
+        self.pos = pos as usize;
+        Ok(pos)
+    }
+}
+
+impl AsyncRead for DataReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let remaining = this.data.get(this.pos..).unwrap_or_default();
+        let read = remaining.len().min(buf.len());
+        buf[..read].copy_from_slice(&remaining[..read]);
+        this.pos += read;
+        Poll::Ready(Ok(read))
+    }
+}
+
+impl AsyncSeek for DataReader {
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<std::io::Result<u64>> {
+        let this = self.get_mut();
+        let pos = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::End(offset) => this.data.len() as i64 + offset,
+            SeekFrom::Current(offset) => this.pos as i64 + offset,
+        };
+        Poll::Ready(this.seek_to(pos))
+    }
+}
+
+impl AsyncSeekForward for DataReader {
+    fn poll_seek_forward(
+        self: Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+        offset: u64,
+    ) -> Poll<std::io::Result<u64>> {
+        let this = self.get_mut();
+        let pos = this.pos as i64 + offset as i64;
+        Poll::Ready(this.seek_to(pos))
+    }
+}
+
+impl Reader for DataReader {}
+
+/// A wrapper around directories to read them
+struct DirReader(Vec<PathBuf>);
+
+impl Stream for DirReader {
+    type Item = PathBuf;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        Poll::Ready(this.0.pop())
+    }
+}
+
+/// A custom asset reader for the embedded assets.
+/// It is used as one of the `AssetLayers`, so the files that are not embedded
+/// are searched for in the next layer.
+pub(crate) struct EmbeddedAssetReader {
+    files: HashMap<PathBuf, EmbeddedFile>,
+    dirs: HashMap<PathBuf, Vec<PathBuf>>,
+}
+
+impl EmbeddedAssetReader {
+    pub(crate) fn new() -> Self {
+        let mut reader = Self {
+            files: HashMap::default(),
+            dirs: HashMap::default(),
+        };
+
+        // Index all files in the asset directory and their parent folders
+        for (path, file) in embedded_files() {
+            debug!("Embedding asset: '{}'", path.display());
+            // Meta files are not considered assets, so they are not listed
+            let is_meta = path.extension().is_some_and(|ext| ext == "meta");
+            let mut child = path.as_path();
+            while let Some(parent) = child.parent().filter(|_| !is_meta) {
+                let children = reader.dirs.entry(parent.to_path_buf()).or_default();
+                if !children.iter().any(|c| c == child) {
+                    children.push(child.to_path_buf());
+                }
+                child = parent;
+            }
+            reader.files.insert(path, file);
+        }
+        reader
+    }
+
+    /// Opens an embedded file, if it exists.
+    fn open(&self, path: &Path) -> Option<Result<DataReader, AssetReaderError>> {
+        let file = self.files.get(path)?;
+        Some(contents(file).map(DataReader::new).map_err(|err| {
+            error!(
+                "Failed to read the embedded asset '{}': {}",
+                path.display(),
+                err
+            );
+            AssetReaderError::Io(Arc::new(err))
+        }))
+    }
+}
+
+/// Lists every file in the asset directory.
+#[cfg(not(feature = "compressed"))]
+fn embedded_files() -> Vec<(PathBuf, EmbeddedFile)> {
+    fn rec(dir: &'static Dir, files: &mut Vec<(PathBuf, EmbeddedFile)>) {
+        for file in dir.files() {
+            files.push((file.path().to_path_buf(), file.contents()));
+        }
+        for dir in dir.dirs() {
+            rec(dir, files);
+        }
+    }
+    let mut files = vec![];
+    rec(&ASSET_DIR, &mut files);
+    files
+}
+
+/// Lists every file in the asset pack.
+#[cfg(feature = "compressed")]
+fn embedded_files() -> Vec<(PathBuf, EmbeddedFile)> {
+    pack::files().unwrap_or_else(|err| {
+        error!("{}", err);
+        vec![]
+    })
+}
+
+/// Returns the raw bytes of an embedded file.
+#[cfg(not(feature = "compressed"))]
+fn contents(file: &EmbeddedFile) -> std::io::Result<Cow<'static, [u8]>> {
+    Ok(Cow::Borrowed(file))
+}
+
+/// Decompresses an embedded file, checking its integrity.
+#[cfg(feature = "compressed")]
+fn contents(file: &EmbeddedFile) -> std::io::Result<Cow<'static, [u8]>> {
+    file.contents()
+}
+
+// Here we implement the `AssetReader` trait from bevy, which lets us switch the
+// default reader for our own, automating the handling of the embedded://
+// namespace and allowing us to use the same code regardless of where the method
+impl AssetReader for EmbeddedAssetReader {
+    async fn read<'a>(&'a self, path: &'a Path) -> Result<Box<dyn Reader + 'a>, AssetReaderError> {
+        match self.open(path) {
+            Some(reader) => Ok(Box::new(reader?)),
+            None => Err(AssetReaderError::NotFound(path.to_path_buf())),
+        }
+    }
+
+    async fn read_meta<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> Result<Box<dyn Reader + 'a>, AssetReaderError> {
+        let meta_path = path.with_added_extension("meta");
+        match self.open(&meta_path) {
+            Some(reader) => Ok(Box::new(reader?)),
+            None => Err(AssetReaderError::NotFound(meta_path)),
+        }
+    }
+
+    async fn read_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> Result<Box<PathStream>, AssetReaderError> {
+        match self.dirs.get(path) {
+            Some(paths) => Ok(Box::new(DirReader(paths.clone()))),
+            None => Err(AssetReaderError::NotFound(path.to_path_buf())),
+        }
+    }
+
+    async fn is_directory<'a>(&'a self, path: &'a Path) -> Result<bool, AssetReaderError> {
+        if self.dirs.contains_key(path) {
+            return Ok(true);
+        }
+        if self.files.contains_key(path) {
+            return Ok(false);
+        }
+        Err(AssetReaderError::NotFound(path.to_path_buf()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::io::AsyncSeekForwardExt,
+        tasks::{
+            block_on,
+            futures_lite::{AsyncReadExt, AsyncSeekExt},
+        },
+    };
+
+    use super::*;
+
+    /// Lists every file in the asset folder on disk.
+    fn files(dir: &Path) -> Vec<PathBuf> {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .flat_map(|entry| match entry.path().is_dir() {
+                true => files(&entry.path()),
+                false => vec![entry.path()],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reads_every_asset() {
+        let reader = EmbeddedAssetReader::new();
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        let files = files(&root);
+        assert!(!files.is_empty());
+
+        for file in files {
+            let path = file.strip_prefix(&root).unwrap();
+            assert!(
+                reader.files.contains_key(path),
+                "'{}' is not embedded",
+                path.display()
+            );
+
+            let mut bytes = vec![];
+            block_on(async {
+                let mut data = AssetReader::read(&reader, path).await.unwrap();
+                Reader::read_to_end(data.as_mut(), &mut bytes)
+                    .await
+                    .unwrap();
+            });
+            assert_eq!(bytes, std::fs::read(&file).unwrap());
+        }
+    }
+
+    #[test]
+    fn reads_meta_files() {
+        // There are no meta files in the asset folder, so use an asset instead
+        let mut reader = EmbeddedAssetReader::new();
+        let asset = Path::new("fonts/sans.ttf");
+        let file = reader.files.remove(asset).unwrap();
+        reader
+            .files
+            .insert(asset.with_added_extension("meta"), file);
+
+        block_on(async {
+            let mut bytes = vec![];
+            let mut meta = AssetReader::read_meta(&reader, asset).await.unwrap();
+            Reader::read_to_end(meta.as_mut(), &mut bytes)
+                .await
+                .unwrap();
+            let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+            assert_eq!(bytes, std::fs::read(root.join(asset)).unwrap());
+
+            let Err(AssetReaderError::NotFound(path)) =
+                AssetReader::read_meta(&reader, Path::new("meta/bevy.png")).await
+            else {
+                panic!("The meta file of 'meta/bevy.png' doesn't exist");
+            };
+            assert_eq!(path, Path::new("meta/bevy.png.meta"));
+        });
+    }
+
+    #[test]
+    fn seeks_inside_of_the_data() {
+        let mut data = DataReader::new(Cow::Borrowed(b"hello bevy"));
+        let mut buf = [0; 4];
+
+        block_on(async {
+            data.seek(SeekFrom::Start(6)).await.unwrap();
+            data.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"bevy");
+
+            data.seek(SeekFrom::End(-10)).await.unwrap();
+            data.seek_forward(1).await.unwrap();
+            data.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"ello");
+
+            data.seek(SeekFrom::Current(-4)).await.unwrap();
+            data.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"ello");
+
+            assert!(data.seek(SeekFrom::Current(-10)).await.is_err());
+        });
+    }
+
+    #[test]
+    fn lists_directories() {
+        let reader = EmbeddedAssetReader::new();
+        block_on(async {
+            assert!(AssetReader::is_directory(&reader, Path::new("fonts"))
+                .await
+                .unwrap());
+            let file = Path::new("fonts/sans.ttf");
+            assert!(!AssetReader::is_directory(&reader, file).await.unwrap());
+        });
+    }
+}
@@ -0,0 +1,141 @@
+//! Reads the compressed asset pack generated by `build.rs`.
+//!
+//! The pack starts with the `PACK` magic, a version and the number of files,
+//! all as little endian `u32`. Then, for each file, the index stores the length
+//! of its path (`u32`), the path, the offset and length of its compressed data
+//! (`u64`), its uncompressed size (`u64`) and its blake3 hash (32 bytes). The
+//! deflate compressed data of every file comes after the index.
+
+use std::{
+    borrow::Cow,
+    io::{Error, ErrorKind, Read},
+    path::PathBuf,
+};
+
+use flate2::read::DeflateDecoder;
+
+const MAGIC: &[u8] = b"PACK";
+const VERSION: u32 = 1;
+
+/// The asset pack, included at compile time.
+static PACK: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/assets.pack"));
+
+/// A compressed file inside of the pack.
+#[derive(Clone, Debug)]
+pub struct PackedFile {
+    data: &'static [u8],
+    size: usize,
+    hash: [u8; 32],
+}
+
+impl PackedFile {
+    /// Decompresses the file and checks that it matches the stored hash.
+    pub fn contents(&self) -> std::io::Result<Cow<'static, [u8]>> {
+        let mut data = Vec::with_capacity(self.size);
+        DeflateDecoder::new(self.data).read_to_end(&mut data)?;
+        if data.len() != self.size || blake3::hash(&data).as_bytes() != &self.hash {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "The embedded asset is corrupted, its hash doesn't match",
+            ));
+        }
+        Ok(Cow::Owned(data))
+    }
+}
+
+/// Parses the index of the embedded pack.
+pub fn files() -> std::io::Result<Vec<(PathBuf, PackedFile)>> {
+    parse(PACK)
+}
+
+/// Parses the index of a pack, checking that every file is inside of it.
+fn parse(pack: &'static [u8]) -> std::io::Result<Vec<(PathBuf, PackedFile)>> {
+    let mut cursor = Cursor(pack);
+    if cursor.take(MAGIC.len())? != MAGIC {
+        return Err(invalid("Missing magic bytes"));
+    }
+    let version = cursor.u32()?;
+    if version != VERSION {
+        return Err(invalid(&format!("Unsupported version {}", version)));
+    }
+
+    let count = cursor.u32()?;
+    let mut index = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let len = cursor.u32()? as usize;
+        let path = std::str::from_utf8(cursor.take(len)?)
+            .map_err(|_| invalid("The path is not valid utf8"))?;
+        let offset = cursor.u64()? as usize;
+        let len = cursor.u64()? as usize;
+        let size = cursor.u64()? as usize;
+        let hash = cursor.take(32)?.try_into().expect("32 bytes");
+        index.push((PathBuf::from(path), offset, len, size, hash));
+    }
+
+    let blobs = cursor.0;
+    index
+        .into_iter()
+        .map(|(path, offset, len, size, hash)| {
+            let data = offset
+                .checked_add(len)
+                .and_then(|end| blobs.get(offset..end))
+                .ok_or_else(|| invalid(&format!("'{}' is out of bounds", path.display())))?;
+            Ok((path, PackedFile { data, size, hash }))
+        })
+        .collect()
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("The embedded asset pack is corrupted: {}", msg),
+    )
+}
+
+/// Reads values from the start of a slice.
+struct Cursor(&'static [u8]);
+
+impl Cursor {
+    fn take(&mut self, len: usize) -> std::io::Result<&'static [u8]> {
+        if self.0.len() < len {
+            return Err(invalid("Unexpected end of the index"));
+        }
+        let (data, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(data)
+    }
+
+    fn u32(&mut self) -> std::io::Result<u32> {
+        Ok(u32::from_le_bytes(
+            self.take(4)?.try_into().expect("4 bytes"),
+        ))
+    }
+
+    fn u64(&mut self) -> std::io::Result<u64> {
+        Ok(u64::from_le_bytes(
+            self.take(8)?.try_into().expect("8 bytes"),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corrupted_files_are_rejected() {
+        let (_, file) = files().unwrap().into_iter().next().unwrap();
+        assert!(file.contents().is_ok());
+
+        let mut hash = file.hash;
+        hash[0] ^= 1;
+        let corrupted = PackedFile { hash, ..file };
+        assert!(corrupted.contents().is_err());
+    }
+
+    #[test]
+    fn truncated_packs_are_rejected() {
+        assert!(parse(&PACK[..PACK.len() - 1]).is_err());
+        assert!(parse(b"KCAP").is_err());
+    }
+}
@@ -0,0 +1,292 @@
+//! Stacks several asset sources on top of each other, so that mods can override
+//! files from the base game.
+//! The layers are, from the highest priority to the lowest, the mods enabled in
+//! `GameOptions` (read from `mods/<name>/`), the embedded assets (if enabled)
+//! and the asset folder.
+//!
+//! The layers are chosen once when the game starts, using the saved
+//! `GameOptions`. Mods that are enabled or disabled while the game is running
+//! apply after restarting it.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use bevy::{
+    asset::io::{
+        AssetReader,
+        AssetReaderError,
+        AssetSource,
+        AssetSourceId,
+        ErasedAssetReader,
+        PathStream,
+        Reader,
+    },
+    tasks::futures_lite::{stream, StreamExt},
+};
+
+use crate::prelude::*;
+
+/// The path of the asset folder.
+const ASSET_PATH: &str = "assets";
+/// The folder containing the mods, each one in its own subfolder.
+const MODS_PATH: &str = "mods";
+
+pub(crate) fn plugin(app: &mut App) {
+    if app.is_plugin_added::<AssetPlugin>() {
+        error!("The asset layers plugin must come before bevy's AssetPlugin");
+    }
+
+    // The mod list can't change while the game is running since the sources
+    // are built only once, so it is read directly from the saved options
+    let mods: Vec<String> = GameOptions::load()
+        .mods
+        .into_iter()
+        .filter(|name| {
+            let exists = AssetLayers::available_mods().contains(name);
+            if !exists {
+                warn!("The mod '{}' was not found in '{}'", name, MODS_PATH);
+            }
+            exists
+        })
+        .collect();
+
+    let mut names: Vec<_> = mods.iter().map(|name| format!("mod:{}", name)).collect();
+    #[cfg(feature = "embedded")]
+    names.push("embedded".into());
+    names.push(ASSET_PATH.into());
+    info!("Asset layers: {:?}", names);
+
+    let layers = AssetLayers {
+        names: Arc::new(names),
+        served: default(),
+    };
+
+    app.insert_resource(layers.clone()).register_asset_source(
+        AssetSourceId::Default,
+        AssetSource::build()
+            .with_reader(move || {
+                let mut readers: Vec<_> = mods
+                    .iter()
+                    .map(|name| {
+                        AssetSource::get_default_reader(format!("{}/{}", MODS_PATH, name))()
+                    })
+                    .collect();
+                #[cfg(feature = "embedded")]
+                readers.push(Box::new(super::embedded::EmbeddedAssetReader::new()));
+                readers.push(AssetSource::get_default_reader(ASSET_PATH.into())());
+                Box::new(LayeredAssetReader {
+                    readers,
+                    layers: layers.clone(),
+                })
+            })
+            .with_watcher(AssetSource::get_default_watcher(
+                ASSET_PATH.into(),
+                Duration::from_millis(300),
+            )),
+    );
+}
+
+// Resources
+// ---
+
+/// Lists the layers that assets are read from and which one served each file.
+/// They don't change until the game restarts, even if `GameOptions::mods` does.
+///
+/// # Examples
+///
+/// ```
+/// use game::prelude::*;
+///
+/// fn debug_layers(layers: Res<AssetLayers>) {
+///     for (path, layer) in layers.served() {
+///         info!("'{}' was read from '{}'", path.display(), layer);
+///     }
+/// }
+/// ```
+#[derive(Resource, Clone, Debug, Default)]
+pub struct AssetLayers {
+    names: Arc<Vec<String>>,
+    served: Arc<RwLock<HashMap<PathBuf, usize>>>,
+}
+
+impl AssetLayers {
+    /// Returns the names of the layers, from the highest priority to the
+    /// lowest.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Returns the name of the layer that the asset at `path` was read from,
+    /// if it has been loaded.
+    pub fn served_by(&self, path: impl AsRef<Path>) -> Option<&str> {
+        let served = self.served.read().ok()?;
+        let layer = *served.get(path.as_ref())?;
+        self.names.get(layer).map(String::as_str)
+    }
+
+    /// Returns every asset that has been loaded together with the name of the
+    /// layer it was read from.
+    pub fn served(&self) -> Vec<(PathBuf, &str)> {
+        let Ok(served) = self.served.read() else { return vec![] };
+        served
+            .iter()
+            .map(|(path, layer)| (path.clone(), self.names[*layer].as_str()))
+            .collect()
+    }
+
+    /// Lists the mods that can be enabled in `GameOptions`.
+    pub fn available_mods() -> Vec<String> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let path = bevy::asset::io::file::FileAssetReader::get_base_path().join(MODS_PATH);
+            let Ok(entries) = std::fs::read_dir(path) else { return vec![] };
+            entries
+                .flatten()
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect()
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        vec![]
+    }
+
+    fn record(&self, path: &Path, layer: usize) {
+        if let Ok(mut served) = self.served.write() {
+            served.insert(path.to_path_buf(), layer);
+        }
+    }
+}
+
+// Helpers
+// ---
+
+/// An asset reader that tries each of its readers in order, returning the
+/// first file that is found.
+struct LayeredAssetReader {
+    readers: Vec<Box<dyn ErasedAssetReader>>,
+    layers: AssetLayers,
+}
+
+impl AssetReader for LayeredAssetReader {
+    async fn read<'a>(&'a self, path: &'a Path) -> Result<Box<dyn Reader + 'a>, AssetReaderError> {
+        for (layer, reader) in self.readers.iter().enumerate() {
+            match reader.read(path).await {
+                Err(AssetReaderError::NotFound(_)) => continue,
+                Ok(data) => {
+                    self.layers.record(path, layer);
+                    return Ok(data);
+                },
+                Err(err) => return Err(err),
+            }
+        }
+        Err(AssetReaderError::NotFound(path.to_path_buf()))
+    }
+
+    async fn read_meta<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> Result<Box<dyn Reader + 'a>, AssetReaderError> {
+        for reader in &self.readers {
+            match reader.read_meta(path).await {
+                Err(AssetReaderError::NotFound(_)) => continue,
+                result => return result,
+            }
+        }
+        Err(AssetReaderError::NotFound(
+            path.with_added_extension("meta"),
+        ))
+    }
+
+    async fn read_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> Result<Box<PathStream>, AssetReaderError> {
+        // Directories are merged, so mods can add new files to a folder
+        let mut found = false;
+        let mut paths = vec![];
+        for reader in &self.readers {
+            match reader.read_directory(path).await {
+                Err(AssetReaderError::NotFound(_)) => continue,
+                Ok(mut entries) => {
+                    found = true;
+                    while let Some(entry) = entries.next().await {
+                        if !paths.contains(&entry) {
+                            paths.push(entry);
+                        }
+                    }
+                },
+                Err(err) => return Err(err),
+            }
+        }
+        match found {
+            true => Ok(Box::new(stream::iter(paths))),
+            false => Err(AssetReaderError::NotFound(path.to_path_buf())),
+        }
+    }
+
+    async fn is_directory<'a>(&'a self, path: &'a Path) -> Result<bool, AssetReaderError> {
+        for reader in &self.readers {
+            match reader.is_directory(path).await {
+                Err(AssetReaderError::NotFound(_)) => continue,
+                result => return result,
+            }
+        }
+        Err(AssetReaderError::NotFound(path.to_path_buf()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{asset::io::file::FileAssetReader, tasks::block_on};
+
+    use super::*;
+
+    #[test]
+    fn mods_override_the_base_assets() {
+        let dir = std::env::temp_dir().join(format!("game-layers-{}", std::process::id()));
+        let mod_dir = dir.join("test");
+        std::fs::create_dir_all(mod_dir.join("fonts")).unwrap();
+        std::fs::write(mod_dir.join("fonts/pixel.ttf"), b"modded").unwrap();
+
+        let layers = AssetLayers {
+            names: Arc::new(vec!["mod:test".into(), ASSET_PATH.into()]),
+            served: default(),
+        };
+        let reader = LayeredAssetReader {
+            readers: vec![
+                Box::new(FileAssetReader::new(&mod_dir)),
+                Box::new(FileAssetReader::new(ASSET_PATH)),
+            ],
+            layers: layers.clone(),
+        };
+        let read = |path: &str| {
+            block_on(async {
+                let mut bytes = vec![];
+                let mut data = AssetReader::read(&reader, Path::new(path)).await.unwrap();
+                Reader::read_to_end(data.as_mut(), &mut bytes)
+                    .await
+                    .unwrap();
+                bytes
+            })
+        };
+
+        // Files in the mod replace the base ones, and the rest are still read
+        // from the asset folder
+        assert_eq!(read("fonts/pixel.ttf"), b"modded");
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join(ASSET_PATH);
+        assert_eq!(
+            read("fonts/sans.ttf"),
+            std::fs::read(root.join("fonts/sans.ttf")).unwrap()
+        );
+
+        assert_eq!(layers.served_by("fonts/pixel.ttf"), Some("mod:test"));
+        assert_eq!(layers.served_by("fonts/sans.ttf"), Some(ASSET_PATH));
+        assert_eq!(layers.served_by("meta/bevy.png"), None);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,333 @@
+//! Data driven asset collections declared in a manifest file.
+//! They are loaded through the same pipeline as the `AssetKey` enums, but they
+//! can be modified without recompiling the game.
+
+use std::{any::TypeId, marker::PhantomData};
+
+use bevy::asset::{io::Reader, AssetLoader, LoadContext, LoadState, RecursiveDependencyLoadState};
+use serde::Deserialize;
+
+use super::PendingAssets;
+use crate::prelude::*;
+
+/// Loads the manifest file when the game starts.
+pub(super) fn plugin(app: &mut App) {
+    app.init_asset::<AssetManifest>()
+        .init_asset_loader::<ManifestLoader>()
+        .load_asset::<ManifestAssetKey>()
+        .load_manifest::<Image>("image")
+        .load_manifest::<Font>("font");
+}
+
+/// The manifest file itself.
+#[asset_key(AssetManifest)]
+pub enum ManifestAssetKey {
+    /// The main asset manifest.
+    #[asset = "game.manifest.toml"]
+    Main,
+}
+
+// Assets
+// ---
+
+/// A list of named collections, each of them a map from asset names to paths.
+///
+/// ```toml
+/// [image]
+/// logo = "meta/bevy.png"
+/// ```
+#[derive(Asset, TypePath, Deserialize, Default, Deref)]
+pub struct AssetManifest(HashMap<String, HashMap<String, String>>);
+
+/// Reads an `AssetManifest` from a toml file.
+#[derive(Default)]
+struct ManifestLoader;
+
+impl AssetLoader for ManifestLoader {
+    type Asset = AssetManifest;
+    type Error = anyhow::Error;
+    type Settings = ();
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<AssetManifest> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).await?;
+        let manifest = toml::from_str(std::str::from_utf8(&bytes)?)?;
+        Ok(manifest)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["manifest.toml"]
+    }
+}
+
+// Keys
+// ---
+
+/// An `AssetKey` for assets declared in the `AssetManifest` instead of an enum.
+/// It is identified by the name of the entry.
+///
+/// # Examples
+///
+/// ```
+/// use game::prelude::*;
+///
+/// fn system(fonts: Res<AssetMap<ManifestKey<Font>>>) {
+///     let pixel_font = fonts.get_named("pixel");
+/// }
+/// ```
+#[derive(Reflect)]
+pub struct ManifestKey<A: Asset> {
+    name: String,
+    #[reflect(ignore)]
+    marker: PhantomData<fn() -> A>,
+}
+
+impl<A: Asset> ManifestKey<A> {
+    /// Creates a key from the name of a manifest entry.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            marker: PhantomData,
+        }
+    }
+
+    /// The name of the manifest entry.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<A: Asset> AssetKey for ManifestKey<A> {
+    type Asset = A;
+}
+
+impl<A: Asset> Clone for ManifestKey<A> {
+    fn clone(&self) -> Self {
+        Self::new(self.name.clone())
+    }
+}
+
+impl<A: Asset> PartialEq for ManifestKey<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl<A: Asset> Eq for ManifestKey<A> {}
+
+impl<A: Asset> std::hash::Hash for ManifestKey<A> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
+}
+
+impl<A: Asset> std::fmt::Debug for ManifestKey<A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ManifestKey({})", self.name)
+    }
+}
+
+impl<A: Asset> AssetMap<ManifestKey<A>> {
+    /// Returns a weak clone of the asset handle with this name, if it is in the
+    /// manifest.
+    pub fn get_named(&self, name: &str) -> Option<Handle<A>> {
+        self.0
+            .get(&ManifestKey::new(name))
+            .and_then(|handles| handles.first())
+            .map(|h| h.clone_weak())
+    }
+}
+
+// Helpers
+// ---
+
+/// Commodity function to create an asset map from a table of the manifest.
+pub trait ManifestExt {
+    /// Loads the assets of a manifest table into an `AssetMap<ManifestKey<A>>`.
+    /// When the manifest changes on disk, the collection switches to the new
+    /// assets once they are loaded.
+    fn load_manifest<A: Asset>(&mut self, table: &'static str) -> &mut Self;
+}
+
+impl ManifestExt for App {
+    fn load_manifest<A: Asset>(&mut self, table: &'static str) -> &mut Self {
+        self.insert_resource(AssetMap::<ManifestKey<A>>::from(HashMap::default()))
+            .register_type::<AssetMap<ManifestKey<A>>>()
+            .add_systems(
+                Update,
+                (move |built: Local<bool>,
+                       reloading: Local<Option<HashMap<ManifestKey<A>, Vec<Handle<A>>>>>,
+                       events: EventReader<AssetEvent<AssetManifest>>,
+                       manifests: Res<Assets<AssetManifest>>,
+                       manifest_assets: Res<AssetMap<ManifestAssetKey>>,
+                       asset_server: Res<AssetServer>,
+                       map: ResMut<AssetMap<ManifestKey<A>>>,
+                       pending: ResMut<PendingAssets>| {
+                    build_map(
+                        table,
+                        built,
+                        reloading,
+                        events,
+                        manifests,
+                        manifest_assets,
+                        asset_server,
+                        map,
+                        pending,
+                    )
+                })
+                .before(super::check_loaded),
+            )
+    }
+}
+
+/// Fills an `AssetMap` with the entries of a manifest table once it is loaded.
+/// The collection is marked as pending so the game waits for it to load.
+/// When the manifest is modified, the new entries are loaded in the background
+/// and the previous ones are kept until all of them are ready.
+fn build_map<A: Asset>(
+    table: &str,
+    mut built: Local<bool>,
+    mut reloading: Local<Option<HashMap<ManifestKey<A>, Vec<Handle<A>>>>>,
+    mut events: EventReader<AssetEvent<AssetManifest>>,
+    manifests: Res<Assets<AssetManifest>>,
+    manifest_assets: Res<AssetMap<ManifestAssetKey>>,
+    asset_server: Res<AssetServer>,
+    mut map: ResMut<AssetMap<ManifestKey<A>>>,
+    mut pending: ResMut<PendingAssets>,
+) {
+    let handle = manifest_assets.get(&ManifestAssetKey::Main);
+    let modified = events.read().any(|e| e.is_modified(&handle));
+    if let Some(manifest) = manifests.get(&handle).filter(|_| modified || !*built) {
+        let entries = load_table(manifest, table, &asset_server);
+        if *built {
+            info!("Asset manifest modified, reloading the '{}' table", table);
+            *reloading = Some(entries);
+        } else {
+            map.0 = entries;
+            *built = true;
+
+            let id = TypeId::of::<AssetMap<ManifestKey<A>>>();
+            if !pending.contains(&id) {
+                pending.push(id);
+            }
+        }
+    }
+
+    // Switch to the reloaded entries once every asset is ready
+    let Some(entries) = reloading.as_ref() else { return };
+    let mut handles = entries.values().flatten();
+    let failed = handles.clone().any(|handle| {
+        matches!(
+            asset_server.get_load_states(handle.id()),
+            Some((LoadState::Failed(_), ..) | (.., RecursiveDependencyLoadState::Failed(_)))
+        )
+    });
+    if failed {
+        warn!(
+            "Failed to reload the '{}' manifest table, keeping the previous assets",
+            table
+        );
+        *reloading = None;
+    } else if handles.all(|handle| asset_server.is_loaded_with_dependencies(handle)) {
+        map.0 = reloading.take().unwrap_or_default();
+    }
+}
+
+/// Requests every asset of a manifest table.
+fn load_table<A: Asset>(
+    manifest: &AssetManifest,
+    table: &str,
+    asset_server: &AssetServer,
+) -> HashMap<ManifestKey<A>, Vec<Handle<A>>> {
+    manifest
+        .get(table)
+        .map(|entries| {
+            entries
+                .iter()
+                .map(|(name, path)| (ManifestKey::new(name), vec![asset_server.load(path)]))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Updates the app until the condition is met or it runs out of tries.
+    fn update_until(app: &mut App, condition: impl Fn(&World) -> bool) -> bool {
+        for _ in 0..500 {
+            app.update();
+            if condition(app.world()) {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    fn set_main(app: &mut App, handle: &Handle<AssetManifest>, path: &str) {
+        let mut manifests = app.world_mut().resource_mut::<Assets<AssetManifest>>();
+        let manifest = manifests.get_mut(handle).expect("loaded manifest");
+        manifest.0.insert(
+            "manifest".into(),
+            HashMap::from_iter([("main".into(), path.into())]),
+        );
+    }
+
+    fn main_entry(world: &World) -> Option<AssetId<AssetManifest>> {
+        world
+            .resource::<AssetMap<ManifestKey<AssetManifest>>>()
+            .get_named("main")
+            .map(|handle| handle.id())
+    }
+
+    #[test]
+    fn reloads_modified_manifests() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_resource::<PendingAssets>()
+            .init_asset::<AssetManifest>()
+            .init_asset_loader::<ManifestLoader>()
+            .load_asset::<ManifestAssetKey>()
+            .load_manifest::<AssetManifest>("manifest");
+
+        let handle = app
+            .world()
+            .resource::<AssetMap<ManifestAssetKey>>()
+            .get(&ManifestAssetKey::Main);
+        assert!(update_until(&mut app, |world| {
+            world.resource::<Assets<AssetManifest>>().contains(&handle)
+        }));
+        app.update();
+        assert_eq!(main_entry(app.world()), None);
+
+        // The map switches over once the new entries are loaded
+        set_main(&mut app, &handle, "game.manifest.toml");
+        assert!(update_until(&mut app, |world| {
+            main_entry(world) == Some(handle.id())
+        }));
+
+        // Entries that fail to load don't replace the previous ones
+        set_main(&mut app, &handle, "missing.manifest.toml");
+        let missing = app
+            .world()
+            .resource::<AssetServer>()
+            .load::<AssetManifest>("missing.manifest.toml");
+        assert!(update_until(&mut app, |world| {
+            matches!(
+                world.resource::<AssetServer>().load_state(&missing),
+                LoadState::Failed(_)
+            )
+        }));
+        app.update();
+        assert_eq!(main_entry(app.world()), Some(handle.id()));
+    }
+}
@@ -13,4 +13,8 @@ pub enum MetaAssetKey {
     /// The logo of the bevy game engine.
     #[asset = "meta/bevy.png"]
     BevyLogo,
+    /// A pixelated version of the bevy logo, without smoothing when scaled.
+    #[asset = "meta/pixelbevy.png"]
+    #[sampler = "nearest"]
+    PixelBevyLogo,
 }
@@ -5,18 +5,38 @@ use crate::prelude::*;
 pub mod data;
 pub mod later;
 pub mod sets;
+pub mod slots;
 pub mod states;
+pub mod window;
 
 pub(super) fn plugin(app: &mut App) {
-    app.add_plugins((data::plugin, later::plugin, sets::plugin, states::plugin));
+    app.add_plugins((
+        data::plugin,
+        later::plugin,
+        sets::plugin,
+        slots::plugin,
+        states::plugin,
+        window::plugin,
+    ));
 }
 
 /// The prelude of this module.
 pub mod prelude {
     pub use super::{
-        data::{GameOptions, Persistent, SaveData},
+        data::{
+            Format,
+            GameOptions,
+            Persistent,
+            PersistentExt,
+            PersistentRegistration,
+            Protection,
+            SaveData,
+            SavePolicy,
+        },
         later::LaterCommandExt,
         sets::{on_setup, PlaySet, SetupCommandExt},
+        slots::{SaveSlots, SlotInfo},
         states::GameState,
+        window::DisplayMode,
     };
 }
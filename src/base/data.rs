@@ -1,22 +1,68 @@
 //! Defines persistent data structures.
 //! For a more complete solution, look at <https://github.com/umut-sahin/bevy-persistent>
 
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+use std::{sync::OnceLock, time::Duration};
+
+use base64::prelude::*;
+use bevy::{
+    reflect::GetTypeRegistration,
+    tasks::{block_on, futures_lite::future, IoTaskPool, Task},
+    window::PresentMode,
+};
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, KeyInit};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::prelude::*;
 
-/// The directory where the persistent data should be saved in.
-const DATA_PATH: &str = ".data";
+/// The name of the folder inside of the user data directory where the
+/// persistent data is saved.
+#[cfg(not(target_arch = "wasm32"))]
+const GAME_NAME: &str = env!("CARGO_PKG_NAME");
+/// An environment variable that overrides the save directory.
+#[cfg(not(target_arch = "wasm32"))]
+const DATA_DIR_ENV: &str = "GAME_DATA_DIR";
+/// A command line flag that overrides the save directory, used as
+/// `--data-dir <path>` or `--data-dir=<path>`. It takes precedence over the
+/// environment variable.
+#[cfg(not(target_arch = "wasm32"))]
+const DATA_DIR_FLAG: &str = "--data-dir";
+/// The directory, relative to where the game was launched, where older
+/// versions saved the persistent data.
+#[cfg(not(target_arch = "wasm32"))]
+const LEGACY_DATA_PATH: &str = ".data";
+/// The directory where the persistent data is saved, see `data_dir`.
+#[cfg(not(target_arch = "wasm32"))]
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+/// The key that stores the version of the data inside of the saved file.
+const VERSION_KEY: &str = "_version";
+/// The folder inside of the save directory that contains the backups.
+const BACKUPS_PATH: &str = "backups";
+/// The secret used to sign and encrypt protected data. Set `GAME_SAVE_KEY`
+/// when building releases, since the default is public. The build script warns
+/// about release builds without it.
+const SAVE_SECRET: &str = match option_env!("GAME_SAVE_KEY") {
+    Some(key) => key,
+    None => env!("CARGO_PKG_NAME"),
+};
+/// The header of signed data, followed by the signature.
+const SIGNATURE_HEADER: &str = "# signature: ";
 
 pub(super) fn plugin(app: &mut App) {
     #[cfg(not(target_arch = "wasm32"))]
-    if let Err(e) = std::fs::create_dir_all(DATA_PATH) {
-        warn!("Couldn't create the save directory {}: {}", DATA_PATH, e);
+    if let Err(e) = std::fs::create_dir_all(data_dir()) {
+        warn!(
+            "Couldn't create the save directory {}: {}",
+            data_dir().display(),
+            e
+        );
     };
-    app.insert_resource(SaveData::load())
-        .insert_resource(GameOptions::load())
-        .add_systems(OnEnter(GameState::Startup), init)
-        .add_systems(Update, on_resize.run_if(on_event::<WindowResized>));
+
+    // Resources that derive `Persistent` are added automatically
+    for registration in inventory::iter::<PersistentRegistration> {
+        (registration.0)(app);
+    }
 }
 
 // Resources
@@ -24,7 +70,11 @@ pub(super) fn plugin(app: &mut App) {
 
 /// Stores options that can be configured on the menu, related to accesibility
 /// and customization.
-#[derive(Reflect, Resource, Serialize, Deserialize)]
+/// The options are saved a second after they stop changing, so that resizing
+/// the window doesn't write them every frame.
+#[derive(Reflect, Resource, Serialize, Deserialize, Persistent)]
+#[persistent(version = 3, migrate = migrate_options)]
+#[persistent(policy = SavePolicy::Debounced(1.))]
 pub struct GameOptions {
     /// The user configurable color palette of the game.
     pub palette: ColorPalette,
@@ -32,57 +82,459 @@ pub struct GameOptions {
     pub resizable: bool,
     /// The last saved resolution of the window
     pub resolution: UVec2,
+    /// The last position of the window in physical pixels, or `None` to
+    /// center it
+    pub position: Option<IVec2>,
+    /// The name of the monitor the window was on
+    pub monitor: Option<String>,
+    /// If the window is windowed or fullscreen
+    pub mode: DisplayMode,
+    /// Controls vsync
+    pub present_mode: PresentMode,
+    /// Overrides the scale factor of the monitor
+    pub scale_factor: Option<f32>,
+    /// The volume of each audio bus
+    pub volume: VolumeOptions,
+    /// The enabled mods, from the highest priority to the lowest. Each one is
+    /// a folder inside of `mods/` whose files override the base assets.
+    /// Changes are applied when the game restarts.
+    pub mods: Vec<String>,
 }
 
-persistent!(GameOptions);
-
 impl Default for GameOptions {
     fn default() -> Self {
         Self {
             palette: ColorPalette::default(),
             resizable: false,
             resolution: UVec2::new(600, 600),
+            position: None,
+            monitor: None,
+            mode: DisplayMode::Windowed,
+            present_mode: PresentMode::AutoVsync,
+            scale_factor: None,
+            volume: VolumeOptions::default(),
+            mods: vec![],
         }
     }
 }
 
 /// Used to store information about the player, the level and game progress.
+/// It is saved in the active slot of `SaveSlots`. Since the play time changes
+/// every frame, it is saved when leaving `GameState::Play` or exiting.
 #[derive(Reflect, Resource, Serialize, Deserialize, Default)]
 pub struct SaveData {
+    /// Information about the slot, like the play time.
+    pub info: SlotInfo,
     /// Placeholder.
     pub test: bool,
+    /// The slot where the data is saved. It is empty for the default one.
+    #[serde(skip)]
+    #[reflect(ignore)]
+    pub(super) slot: String,
 }
 
-persistent!(SaveData);
+impl Persistent for SaveData {
+    const POLICY: SavePolicy = SavePolicy::Debounced(1.);
+    // Use `Protection::Signed` to detect edited saves, for example when the
+    // game has leaderboards. Saves from before enabling it are rejected.
+    const PROTECTION: Protection = Protection::None;
+    const VERSION: u32 = 1;
+
+    fn path() -> &'static str {
+        "SaveData"
+    }
+
+    fn key(&self) -> String {
+        super::slots::slot_key(&self.slot)
+    }
+
+    // The slot is not saved with the data, so it is kept when reloading
+    fn reload(&mut self) {
+        let slot = std::mem::take(&mut self.slot);
+        *self = load_key(&super::slots::slot_key(&slot));
+        self.slot = slot;
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        *self = Self {
+            slot: std::mem::take(&mut self.slot),
+            ..default()
+        };
+        Ok(())
+    }
+
+    fn migrate(version: u32, mut data: toml::Value) -> Result<toml::Value> {
+        let save = data
+            .as_table_mut()
+            .context("The save data is not a table")?;
+        match version {
+            // Added the slot information
+            0 => {
+                save.insert("info".into(), toml::Value::try_from(SlotInfo::default())?);
+            },
+            _ => anyhow::bail!("Unknown version {}", version),
+        }
+        Ok(data)
+    }
+}
 
 // Systems
 // ---
 
-/// When the game starts, set the window resolution and resizability to the
-/// value read in the options
-fn init(mut window: Query<&mut Window, With<PrimaryWindow>>, options: Res<GameOptions>) {
-    let mut window = single_mut!(window);
-    let res = options.resolution.as_vec2();
-    window.resolution.set(res.x, res.y);
-    window.resizable = options.resizable;
-}
+/// Saves a persistent resource following its `SavePolicy`. `Immediate` data
+/// starts saving at the end of the frame where it changed, and `Debounced` data
+/// after its delay, both in the background. When the app exits, every pending
+/// change is saved before closing.
+fn save_persistent<T: Persistent>(
+    data: Res<T>,
+    time: Res<Time<Real>>,
+    mut exit: EventReader<AppExit>,
+    mut state: Local<SaveState>,
+) {
+    let name = T::type_path();
+    let exiting = exit.read().count() > 0;
+    if data.is_changed() && !data.is_added() && !matches!(T::POLICY, SavePolicy::Manual) {
+        state.changed = Some(time.elapsed());
+    }
 
-/// When the window is resized, updates the saved resolution
-fn on_resize(mut resize_events: EventReader<WindowResized>, mut options: ResMut<GameOptions>) {
-    for event in resize_events.read() {
-        let _ = options.update(|options| {
-            options.resolution = UVec2::new(event.width as u32, event.height as u32);
-        });
+    // Only one save can be running at a time
+    if let Some(task) = &mut state.task {
+        let result = match exiting {
+            true => Some(block_on(task)),
+            false => block_on(future::poll_once(task)),
+        };
+        match result {
+            Some(Ok(_)) => debug!("{} updated", name),
+            Some(Err(e)) => error!("Failed to save {}: {:?}", name, e),
+            None => return,
+        }
+        state.task = None;
     }
+
+    let Some(changed) = state.changed else { return };
+    let due = match T::POLICY {
+        SavePolicy::Immediate => true,
+        SavePolicy::Debounced(secs) => (time.elapsed() - changed).as_secs_f32() >= secs,
+        SavePolicy::OnExit | SavePolicy::Manual => false,
+    };
+    if !due && !exiting {
+        return;
+    }
+    state.changed = None;
+
+    // `update` and `reset` may have saved the same data already, which is
+    // checked when writing so that the saved file is not read in the frame
+    let key = data.key();
+    let data = match serialize(&*data) {
+        Ok(data) => data,
+        Err(e) => {
+            error!("Failed to serialize data for {}: {:?}", name, e);
+            return;
+        },
+    };
+    if exiting {
+        if let Err(e) = write_if_changed::<T>(&key, data) {
+            error!("Failed to save {}: {:?}", name, e);
+        }
+        return;
+    }
+    state.task = Some(IoTaskPool::get().spawn(async move { write_if_changed::<T>(&key, data) }));
 }
 
 // Helpers
 // ---
 
-// TODO: Look into macro_rules_attribute to derive this instead
+/// The formats that `Persistent` resources can be saved in.
+/// Every format is converted to a toml table before deserializing, so that
+/// migrations work the same way regardless of the format.
+///
+/// Because of that, every format has the limits of toml, and saving data
+/// that doesn't fit in them fails:
+/// - `None` can only be the value of a field, not an item of a list or map.
+/// - Map keys must be strings (or unit enum variants).
+/// - Integers must fit in an `i64`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// <https://toml.io>, easy to edit by hand.
+    Toml,
+    /// <https://github.com/ron-rs/ron>, the Rust Object Notation.
+    Ron,
+    /// <https://www.json.org>, useful to share the data with other tools.
+    Json,
+    /// <https://postcard.jamesmunns.com>, not readable without the game.
+    /// It stores the toml table with its field names and types, so it is not
+    /// much smaller than the text formats. It is saved as base64 so that it
+    /// can be stored as text, like in `localStorage`.
+    Binary,
+}
+
+impl Format {
+    /// Returns the file extension of the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Toml => "toml",
+            Format::Ron => "ron",
+            Format::Json => "json",
+            Format::Binary => "bin",
+        }
+    }
+
+    /// Parses data in this format.
+    pub fn parse(&self, data: &str) -> Result<toml::Value> {
+        Ok(match self {
+            Format::Toml => toml::Value::Table(toml::from_str(data).context("Invalid toml")?),
+            Format::Ron => ron::from_str(data).context("Invalid ron")?,
+            Format::Json => serde_json::from_str(data).context("Invalid json")?,
+            Format::Binary => {
+                let data = BASE64_STANDARD
+                    .decode(data.trim())
+                    .context("Invalid base64")?;
+                postcard::from_bytes::<BinaryValue>(&data)
+                    .context("Invalid binary data")?
+                    .try_into()?
+            },
+        })
+    }
+
+    /// Serializes data in this format.
+    pub fn write(&self, data: &toml::Value) -> Result<String> {
+        Ok(match self {
+            Format::Toml => toml::to_string(data)?,
+            Format::Ron => ron::ser::to_string_pretty(data, default())?,
+            Format::Json => serde_json::to_string_pretty(data)?,
+            Format::Binary => {
+                BASE64_STANDARD.encode(postcard::to_stdvec(&BinaryValue::from(data))?)
+            },
+        })
+    }
+}
+
+/// A copy of `toml::Value` that saves the type of each value.
+/// Binary formats like postcard need to know the types to deserialize them,
+/// but migrations read the data without knowing its type.
+#[derive(Serialize, Deserialize)]
+enum BinaryValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Datetime(String),
+    Array(Vec<BinaryValue>),
+    Table(Vec<(String, BinaryValue)>),
+}
+
+impl From<&toml::Value> for BinaryValue {
+    fn from(value: &toml::Value) -> Self {
+        match value {
+            toml::Value::String(v) => Self::String(v.clone()),
+            toml::Value::Integer(v) => Self::Integer(*v),
+            toml::Value::Float(v) => Self::Float(*v),
+            toml::Value::Boolean(v) => Self::Boolean(*v),
+            toml::Value::Datetime(v) => Self::Datetime(v.to_string()),
+            toml::Value::Array(v) => Self::Array(v.iter().map(Self::from).collect()),
+            toml::Value::Table(v) => Self::Table(
+                v.iter()
+                    .map(|(key, value)| (key.clone(), Self::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl TryFrom<BinaryValue> for toml::Value {
+    type Error = anyhow::Error;
+
+    fn try_from(value: BinaryValue) -> Result<Self> {
+        Ok(match value {
+            BinaryValue::String(v) => Self::String(v),
+            BinaryValue::Integer(v) => Self::Integer(v),
+            BinaryValue::Float(v) => Self::Float(v),
+            BinaryValue::Boolean(v) => Self::Boolean(v),
+            BinaryValue::Datetime(v) => Self::Datetime(v.parse().context("Invalid datetime")?),
+            // `Self::try_from` would be the inherent method of `toml::Value`
+            BinaryValue::Array(v) => Self::Array(
+                v.into_iter()
+                    .map(TryInto::try_into)
+                    .collect::<Result<_>>()?,
+            ),
+            BinaryValue::Table(v) => Self::Table(
+                v.into_iter()
+                    .map(|(key, value)| Ok((key, value.try_into()?)))
+                    .collect::<Result<_>>()?,
+            ),
+        })
+    }
+}
+
+/// Protects saved data against being edited outside of the game, for example
+/// to cheat on leaderboards. Data that was tampered with is rejected when
+/// loading, and the newest valid backup is used instead.
+///
+/// The keys are derived at build time from the `GAME_SAVE_KEY` environment
+/// variable. Changing it invalidates every protected save.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Protection {
+    /// The data is saved as is.
+    None,
+    /// A keyed hash of the data is saved with it. It can still be read, but
+    /// any change is detected.
+    Signed,
+    /// The data is encrypted and authenticated, so it can't be read either.
+    Encrypted,
+}
+
+impl Protection {
+    /// Protects serialized data before it is saved.
+    pub fn seal(&self, data: String) -> Result<String> {
+        Ok(match self {
+            Protection::None => data,
+            Protection::Signed => {
+                let signature = blake3::keyed_hash(signing_key(), data.as_bytes());
+                format!("{}{}\n{}", SIGNATURE_HEADER, signature.to_hex(), data)
+            },
+            Protection::Encrypted => {
+                let nonce: [u8; 12] = rand::random();
+                let mut sealed = nonce.to_vec();
+                sealed.extend(
+                    cipher()
+                        .encrypt(&nonce.into(), data.as_bytes())
+                        .map_err(|_| anyhow::anyhow!("Failed to encrypt the data"))?,
+                );
+                BASE64_STANDARD.encode(sealed)
+            },
+        })
+    }
+
+    /// Checks that saved data was not modified and returns its contents.
+    pub fn open(&self, data: &str) -> Result<String> {
+        Ok(match self {
+            Protection::None => data.into(),
+            Protection::Signed => {
+                let (header, data) = data
+                    .strip_prefix(SIGNATURE_HEADER)
+                    .and_then(|data| data.split_once('\n'))
+                    .context("The data is not signed")?;
+                let signature =
+                    blake3::Hash::from_hex(header.trim()).context("Invalid signature")?;
+                // Comparing hashes takes constant time
+                if blake3::keyed_hash(signing_key(), data.as_bytes()) != signature {
+                    anyhow::bail!("The data was modified outside of the game");
+                }
+                data.into()
+            },
+            Protection::Encrypted => {
+                let sealed = BASE64_STANDARD
+                    .decode(data.trim())
+                    .context("The data is not encrypted")?;
+                if sealed.len() < 12 {
+                    anyhow::bail!("The encrypted data is too short");
+                }
+                let (nonce, data) = sealed.split_at(12);
+                let data = cipher()
+                    .decrypt(nonce.into(), data)
+                    .map_err(|_| anyhow::anyhow!("The data was modified outside of the game"))?;
+                String::from_utf8(data).context("The decrypted data is not valid utf8")?
+            },
+        })
+    }
+}
+
+/// Controls when a `Persistent` resource is written to disk.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SavePolicy {
+    /// Saved as soon as it is updated. Changes made directly through `ResMut`
+    /// are saved in the background at the end of the frame, so data that
+    /// changes every frame should use `Debounced` instead.
+    Immediate,
+    /// Saved in the background once it hasn't changed for some seconds.
+    Debounced(f32),
+    /// Saved only when the app exits.
+    OnExit,
+    /// Saved only when `Persistent::persist` is called.
+    Manual,
+}
+
+/// Tracks the pending changes of a persistent resource.
+#[derive(Default)]
+struct SaveState {
+    changed: Option<Duration>,
+    task: Option<Task<Result<()>>>,
+}
+
+/// Adds a `Persistent` resource to every app when the game starts.
+/// `#[derive(Persistent)]` creates one for each type, so it is rarely needed
+/// directly.
+pub struct PersistentRegistration(fn(&mut App));
+
+impl PersistentRegistration {
+    /// Creates the registration of a resource.
+    pub const fn new<T: Persistent + GetTypeRegistration>() -> Self {
+        Self(init_registered::<T>)
+    }
+}
+
+inventory::collect!(PersistentRegistration);
+
+fn init_registered<T: Persistent + GetTypeRegistration>(app: &mut App) {
+    app.init_persistent::<T>();
+}
+
+/// Adds persistent resources to the app.
+pub trait PersistentExt {
+    /// Loads a persistent resource, registers its type and adds the systems
+    /// that save it according to its `SavePolicy`. It does nothing if the
+    /// resource was already added, for example automatically.
+    fn init_persistent<T: Persistent + GetTypeRegistration>(&mut self) -> &mut Self;
+    /// Same as `init_persistent`, but with data that was already loaded.
+    fn insert_persistent<T: Persistent + GetTypeRegistration>(&mut self, data: T) -> &mut Self;
+}
+
+impl PersistentExt for App {
+    fn init_persistent<T: Persistent + GetTypeRegistration>(&mut self) -> &mut Self {
+        if self.world().contains_resource::<T>() {
+            return self;
+        }
+        self.insert_persistent(T::load())
+    }
+
+    fn insert_persistent<T: Persistent + GetTypeRegistration>(&mut self, data: T) -> &mut Self {
+        self.register_type::<T>()
+            .insert_resource(data)
+            .add_systems(Last, save_persistent::<T>)
+    }
+}
+
+/// Upgrades saved `GameOptions` from older versions.
+fn migrate_options(version: u32, mut data: toml::Value) -> Result<toml::Value> {
+    let options = data.as_table_mut().context("The options are not a table")?;
+    match version {
+        // Added the mod list
+        0 => {
+            options.entry("mods").or_insert(toml::Value::Array(vec![]));
+        },
+        // Added the window mode and vsync
+        1 => {
+            options.entry("mode").or_insert("Windowed".into());
+            options.entry("present_mode").or_insert("AutoVsync".into());
+        },
+        // Added the audio volume
+        2 => {
+            let volume = toml::Value::try_from(VolumeOptions::default())?;
+            options.entry("volume").or_insert(volume);
+        },
+        _ => anyhow::bail!("Unknown version {}", version),
+    }
+    Ok(data)
+}
 
 /// Indicates that a `Resource` can be saved and loaded from disk.
-/// This is implemented automatically when using `persistent`.
+/// Use `#[derive(Persistent)]` to implement it, see the macro for the options.
+/// Derived resources are added to the app automatically, while manual
+/// implementations need `PersistentExt::init_persistent`.
+///
+/// The data is saved together with its version. When the fields of the
+/// resource change, increase the version and add a migration that upgrades the
+/// previous format, so that the saved data is not lost.
 ///
 /// # Examples
 ///
@@ -90,11 +542,10 @@ fn on_resize(mut resize_events: EventReader<WindowResized>, mut options: ResMut<
 /// use game::prelude::*;
 /// use serde::{Deserialize, Serialize};
 ///
-/// #[derive(Reflect, Resource, Serialize, Deserialize, Default)]
+/// #[derive(Reflect, Resource, Serialize, Deserialize, Default, Persistent)]
 /// pub struct SomeData {
 ///     pub test: bool,
 /// }
-/// persistent!(SomeData);
 ///
 /// // The persistent data can be accessed in any system.
 /// fn read(data: Res<SomeData>) {
@@ -107,14 +558,56 @@ fn on_resize(mut resize_events: EventReader<WindowResized>, mut options: ResMut<
 ///     data.update(|data| {
 ///         data.test = true;
 ///     });
-///     // This will not until you call `persist` manually.
+///     // This will be saved in the background at the end of the frame, or
+///     // right away with `persist`.
 ///     data.test = false;
 ///     data.persist();
 /// }
+///
+/// // Renaming a field requires a new version and a migration.
+/// #[derive(Reflect, Resource, Serialize, Deserialize, Default, Persistent)]
+/// #[persistent(version = 1, migrate = migrate_other)]
+/// pub struct OtherData {
+///     pub done: bool,
+/// }
+///
+/// fn migrate_other(version: u32, mut data: toml::Value) -> Result<toml::Value> {
+///     let table = data.as_table_mut().context("Not a table")?;
+///     if version == 0 {
+///         let test = table.remove("test").unwrap_or(false.into());
+///         table.insert("done".into(), test);
+///     }
+///     Ok(data)
+/// }
 /// ```
 pub trait Persistent: Resource + Serialize + DeserializeOwned + Default + TypePath {
+    /// The current version of the saved format. Data without a version is
+    /// considered to be version 0.
+    const VERSION: u32 = 0;
+    /// How many of the previously saved versions are kept as backups.
+    const BACKUPS: usize = 3;
+    /// When the resource is saved. Changes made outside of `update` and
+    /// `reset` are only saved if the resource was added with
+    /// `PersistentExt::init_persistent`.
+    const POLICY: SavePolicy = SavePolicy::Immediate;
+    /// The format used to serialize the data.
+    const FORMAT: Format = Format::Toml;
+    /// How the saved data is protected against tampering.
+    const PROTECTION: Protection = Protection::None;
+
     /// Returns the path that this resource needs to write to.
     fn path() -> &'static str;
+    /// Returns the key where the data is currently stored, which is `path`
+    /// with the extension of the format unless the location can change at
+    /// runtime, like with save slots.
+    fn key(&self) -> String {
+        format!("{}.{}", Self::path(), Self::FORMAT.extension())
+    }
+    /// Upgrades data saved with `version` to the next version. It is called in
+    /// a chain until the data reaches the current `VERSION`.
+    fn migrate(version: u32, _data: toml::Value) -> Result<toml::Value> {
+        anyhow::bail!("No migration from version {}", version)
+    }
     /// Reads a resource from disk if it exists. If it doesn't it returns the
     /// default value.
     fn load() -> Self {
@@ -123,64 +616,426 @@ pub trait Persistent: Resource + Serialize + DeserializeOwned + Default + TypePa
         data
     }
     /// Reads the saved value of this resource and overwrites its current value.
+    /// If it can't be read, the newest valid backup is used instead.
     fn reload(&mut self) {
-        #[cfg(not(target_arch = "wasm32"))]
-        let data = {
-            let path = format!("{}/{}.toml", DATA_PATH, Self::path());
-            std::fs::read_to_string(path).ok()
+        *self = load_key(&self.key());
+    }
+    /// Parses saved data, checking that it wasn't tampered with and migrating
+    /// it from older versions if needed.
+    fn decode(data: &str) -> Result<Self> {
+        let data = Self::PROTECTION.open(data)?;
+        let toml::Value::Table(mut table) = Self::FORMAT.parse(&data)? else {
+            anyhow::bail!("The data is not a table");
         };
-
-        #[cfg(target_arch = "wasm32")]
-        let data = (|| {
-            let local_storage = web_sys::window()?.local_storage().ok()??;
-            local_storage.get(Self::path()).ok()?
-        })();
-
-        *self = match data {
-            Some(data) => toml::from_str(&data).unwrap_or_default(),
-            None => Self::default(),
+        let version = match table.remove(VERSION_KEY) {
+            Some(version) => version
+                .as_integer()
+                .and_then(|v| u32::try_from(v).ok())
+                .context("Invalid version")?,
+            None => 0,
         };
+        if version > Self::VERSION {
+            anyhow::bail!(
+                "The data has version {}, which is newer than the supported {}",
+                version,
+                Self::VERSION
+            );
+        }
+
+        let mut data = toml::Value::Table(table);
+        for version in version..Self::VERSION {
+            data = Self::migrate(version, data)
+                .with_context(|| format!("Failed to migrate from version {}", version))?;
+        }
+        data.try_into()
+            .context("The data doesn't match the current format")
+    }
+    /// Serializes the data of this resource together with its version.
+    fn encode(&self) -> Result<String> {
+        Self::PROTECTION.seal(serialize(self)?)
     }
     /// Serializes the data of this resource and saves it.
     fn persist(&self) -> Result<()> {
         let name = Self::type_path();
-        let data = toml::to_string(self)
+        let data = self
+            .encode()
             .with_context(|| format!("Failed to serialize data for {}", name))?;
-
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            let path = format!("{}/{}.toml", DATA_PATH, Self::path());
-            std::fs::write(path.clone(), data)
-                .with_context(|| format!("Failed to save serialized data for {}", name))?;
-        }
-
-        #[cfg(target_arch = "wasm32")]
-        {
-            let local_storage = web_sys::window()
-                .context("Error getting the JavaScript window")?
-                .local_storage()
-                .ok()
-                .context("No access to localStorage")?
-                .context("No access to localStorage")?;
-            local_storage
-                .set(Self::path(), &data)
-                .ok()
-                .with_context(|| format!("Failed to save serialized data for {}", name))?;
-        }
-
+        write_key(&self.key(), &data, Self::BACKUPS)
+            .with_context(|| format!("Failed to save serialized data for {}", name))?;
         debug!("{} updated", name);
         Ok(())
     }
 
     /// Mutates the values of the resource using a closure and writes the result
-    /// to disk after it is done.
+    /// to disk after it is done (or later, depending on the `SavePolicy`).
     fn update(&mut self, f: impl Fn(&mut Self)) -> Result<()> {
         f(self);
-        self.persist()
+        match Self::POLICY {
+            SavePolicy::Immediate => self.persist(),
+            _ => Ok(()),
+        }
     }
-    /// Returns the resource to its default value and saves it.
+    /// Returns the resource to its default value and saves it (or schedules it
+    /// to be saved, depending on the `SavePolicy`).
     fn reset(&mut self) -> Result<()> {
         *self = Self::default();
-        self.persist()
+        match Self::POLICY {
+            SavePolicy::Immediate => self.persist(),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Reads the data saved with a key, or the newest valid backup if it can't be
+/// read. If there is none, the default value is returned.
+fn load_key<T: Persistent>(key: &str) -> T {
+    let name = T::type_path();
+    let saved = read_key(key);
+    if let Some(data) = &saved {
+        match T::decode(data) {
+            Ok(data) => return data,
+            Err(e) => error!("Couldn't read the saved {}: {:?}", name, e),
+        }
+    }
+
+    // The main file may be corrupted or missing if the game crashed while
+    // saving, so try the backups from newest to oldest
+    for (i, data) in read_backups(key).iter().enumerate() {
+        match T::decode(data) {
+            Ok(data) => {
+                warn!("Restored {} from the backup {}", name, i + 1);
+                return data;
+            },
+            Err(e) => warn!("Couldn't read the backup {} of {}: {:?}", i + 1, name, e),
+        }
+    }
+
+    if saved.is_some() {
+        error!("There are no valid backups of {}, using the default", name);
+    }
+    T::default()
+}
+
+/// Serializes a resource together with its version, before protecting it.
+/// It fails if some of the data can't be represented as toml (see `Format`).
+fn serialize<T: Persistent>(data: &T) -> Result<String> {
+    let mut table = toml::Table::try_from(data).context("The data can't be saved as toml")?;
+
+    // Fields that fail because of a `None`, like a list with a `None` in it,
+    // are skipped by toml instead of failing, so check that nothing was lost
+    let restored: T = toml::Value::Table(table.clone())
+        .try_into()
+        .context("The data can't be restored from toml")?;
+    if serde_json::to_value(&restored)? != serde_json::to_value(data)? {
+        anyhow::bail!("Some of the data was lost when converting it to toml");
+    }
+
+    table.insert(VERSION_KEY.into(), i64::from(T::VERSION).into());
+    T::FORMAT.write(&toml::Value::Table(table))
+}
+
+/// Protects and writes serialized data, unless it is the same as the saved
+/// data. It reads the saved file, so it should run in the background.
+/// The data is compared before protecting it, since encrypting it twice gives
+/// different results.
+fn write_if_changed<T: Persistent>(key: &str, data: String) -> Result<()> {
+    let saved = read_key(key).and_then(|saved| T::PROTECTION.open(&saved).ok());
+    if saved.is_some_and(|saved| saved == data) {
+        return Ok(());
+    }
+    write_key(key, &T::PROTECTION.seal(data)?, T::BACKUPS)
+}
+
+// Storage
+// ---
+
+/// The key used to sign protected data.
+fn signing_key() -> &'static [u8; 32] {
+    static KEY: OnceLock<[u8; 32]> = OnceLock::new();
+    KEY.get_or_init(|| {
+        blake3::derive_key("hello-bevy 2024 save signing key", SAVE_SECRET.as_bytes())
+    })
+}
+
+/// The cipher used to encrypt protected data, with a different key than the
+/// signatures.
+fn cipher() -> ChaCha20Poly1305 {
+    static KEY: OnceLock<[u8; 32]> = OnceLock::new();
+    let key = KEY.get_or_init(|| {
+        blake3::derive_key(
+            "hello-bevy 2024 save encryption key",
+            SAVE_SECRET.as_bytes(),
+        )
+    });
+    ChaCha20Poly1305::new(key.into())
+}
+
+/// Returns the directory where the persistent data is saved.
+/// It is a folder inside of the user data directory of the platform (for
+/// example, `$XDG_DATA_HOME/game` on Linux), unless it is overriden with the
+/// `--data-dir` flag or the `GAME_DATA_DIR` environment variable.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn data_dir() -> &'static Path {
+    DATA_DIR.get_or_init(|| {
+        data_dir_override().unwrap_or_else(|| {
+            let dir = user_data_dir();
+            move_legacy_data(&dir);
+            dir
+        })
+    })
+}
+
+/// Overrides the save directory, for example to use a temporary one in tests.
+/// It fails if the directory was already used to load or save data.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_data_dir(dir: impl Into<PathBuf>) -> Result<()> {
+    DATA_DIR
+        .set(dir.into())
+        .map_err(|_| anyhow::anyhow!("The save directory is already in use"))
+}
+
+/// Checks if the save directory was set from the command line or from an
+/// environment variable.
+#[cfg(not(target_arch = "wasm32"))]
+fn data_dir_override() -> Option<PathBuf> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == DATA_DIR_FLAG {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(dir) = arg.strip_prefix(&format!("{}=", DATA_DIR_FLAG)) {
+            return Some(dir.into());
+        }
+    }
+    std::env::var_os(DATA_DIR_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Returns the save directory following the conventions of each platform.
+#[cfg(not(target_arch = "wasm32"))]
+fn user_data_dir() -> PathBuf {
+    let env = |name| {
+        std::env::var_os(name)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+    };
+    #[cfg(target_os = "windows")]
+    let user_dir = env("APPDATA");
+    #[cfg(target_os = "macos")]
+    let user_dir = env("HOME").map(|home| home.join("Library/Application Support"));
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let user_dir =
+        env("XDG_DATA_HOME").or_else(|| env("HOME").map(|home| home.join(".local/share")));
+
+    match user_dir {
+        Some(dir) => dir.join(GAME_NAME),
+        None => {
+            warn!("Couldn't find the user data directory, saving in the current one");
+            LEGACY_DATA_PATH.into()
+        },
+    }
+}
+
+/// Moves the data saved by older versions to the new save directory, if it
+/// doesn't exist yet.
+#[cfg(not(target_arch = "wasm32"))]
+fn move_legacy_data(dir: &Path) {
+    let legacy = Path::new(LEGACY_DATA_PATH);
+    if !legacy.is_dir() || dir.exists() {
+        return;
+    }
+
+    // Renaming fails across file systems, so fall back to a copy
+    let result = dir
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::rename(legacy, dir))
+        .or_else(|_| copy_dir(legacy, dir));
+    match result {
+        Ok(_) => info!(
+            "Moved the saved data from '{}' to '{}'",
+            legacy.display(),
+            dir.display()
+        ),
+        Err(e) => warn!("Couldn't move the saved data to '{}': {}", dir.display(), e),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        match entry.file_type()?.is_dir() {
+            true => copy_dir(&entry.path(), &target)?,
+            false => {
+                std::fs::copy(entry.path(), target)?;
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Reads the data saved with a key, if it exists.
+pub(super) fn read_key(key: &str) -> Option<String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return std::fs::read_to_string(key_path(key)).ok();
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        // Older versions saved the keys without an extension
+        let storage = local_storage().ok()?;
+        let legacy = key.strip_suffix(".toml").unwrap_or(key);
+        storage.get(key).ok()?.or_else(|| storage.get(legacy).ok()?)
+    }
+}
+
+/// Reads the backups of a key, from the newest to the oldest.
+pub(super) fn read_backups(key: &str) -> Vec<String> {
+    (1..).map_while(|i| read_key(&backup_key(key, i))).collect()
+}
+
+/// Saves data with a key, replacing its previous value.
+/// The previous values are kept as rotating backups.
+pub(super) fn write_key(key: &str, data: &str, backups: usize) -> Result<()> {
+    if backups > 0 {
+        for i in (1..backups).rev() {
+            if key_exists(&backup_key(key, i)) {
+                move_key(&backup_key(key, i), &backup_key(key, i + 1))?;
+            }
+        }
+        if let Some(previous) = read_key(key) {
+            set_key(&backup_key(key, 1), &previous)?;
+        }
+    }
+    set_key(key, data)
+}
+
+/// Removes the data saved with a key, including its backups.
+pub(super) fn remove_key(key: &str) -> Result<()> {
+    for i in (1..).take_while(|i| key_exists(&backup_key(key, *i))) {
+        delete_key(&backup_key(key, i))?;
+    }
+    delete_key(key)
+}
+
+/// Lists the keys that are directly inside of a folder, without the folder
+/// prefix.
+pub(super) fn list_keys(folder: &str) -> Vec<String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let Ok(entries) = std::fs::read_dir(data_dir().join(folder)) else {
+            return vec![];
+        };
+        entries
+            .flatten()
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let Ok(storage) = local_storage() else { return vec![] };
+        let prefix = format!("{}/", folder);
+        (0..storage.length().unwrap_or_default())
+            .filter_map(|i| storage.key(i).ok()?)
+            .filter_map(|key| Some(key.strip_prefix(&prefix)?.to_string()))
+            .filter(|name| !name.contains('/'))
+            .collect()
+    }
+}
+
+/// Returns the file where a key is saved.
+#[cfg(not(target_arch = "wasm32"))]
+fn key_path(key: &str) -> PathBuf {
+    data_dir().join(key)
+}
+
+/// Returns the browser storage used to save data on the web.
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Result<web_sys::Storage> {
+    web_sys::window()
+        .context("Error getting the JavaScript window")?
+        .local_storage()
+        .ok()
+        .flatten()
+        .context("No access to localStorage")
+}
+
+/// Returns the key of one of the backups of a key, starting at 1.
+/// The number goes before the extension, like `backups/GameOptions.1.toml`.
+fn backup_key(key: &str, i: usize) -> String {
+    match key.rsplit_once('.') {
+        Some((name, extension)) if !extension.contains('/') => {
+            format!("{}/{}.{}.{}", BACKUPS_PATH, name, i, extension)
+        },
+        _ => format!("{}/{}.{}", BACKUPS_PATH, key, i),
     }
 }
+
+/// Checks if there is data saved with a key.
+fn key_exists(key: &str) -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    return key_path(key).exists();
+
+    #[cfg(target_arch = "wasm32")]
+    return read_key(key).is_some();
+}
+
+/// Saves data with a key.
+/// On native, the data is written to a temporary file that replaces the
+/// previous one when it is done, so that a crash never leaves it half written.
+fn set_key(key: &str, data: &str) -> Result<()> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use std::io::Write;
+
+        let path = key_path(key);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp = path.with_added_extension("tmp");
+        let mut file = std::fs::File::create(&temp)?;
+        file.write_all(data.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(temp, path)?;
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    local_storage()?
+        .set(key, data)
+        .ok()
+        .context("Failed to write to localStorage")?;
+
+    Ok(())
+}
+
+/// Moves the data saved with a key to another one, replacing it.
+fn move_key(from: &str, to: &str) -> Result<()> {
+    #[cfg(not(target_arch = "wasm32"))]
+    std::fs::rename(key_path(from), key_path(to))?;
+
+    #[cfg(target_arch = "wasm32")]
+    if let Some(data) = read_key(from) {
+        set_key(to, &data)?;
+        delete_key(from)?;
+    }
+
+    Ok(())
+}
+
+/// Deletes the data saved with a key.
+fn delete_key(key: &str) -> Result<()> {
+    #[cfg(not(target_arch = "wasm32"))]
+    std::fs::remove_file(key_path(key))?;
+
+    #[cfg(target_arch = "wasm32")]
+    local_storage()?
+        .remove_item(key)
+        .ok()
+        .context("Failed to remove from localStorage")?;
+
+    Ok(())
+}
@@ -0,0 +1,231 @@
+//! Allows to have multiple named save slots for `SaveData`.
+//! Only one slot is active at a time, and it is the one that `SaveData` reads
+//! from and writes to.
+
+use bevy::utils::SystemTime;
+use serde::{Deserialize, Serialize};
+
+use super::data::{list_keys, read_key, remove_key, write_key};
+use crate::prelude::*;
+
+/// The folder inside of the save directory that contains every slot.
+const SLOTS_PATH: &str = "slots";
+/// The slot that is used if none was selected.
+const DEFAULT_SLOT: &str = "default";
+
+pub(super) fn plugin(app: &mut App) {
+    move_legacy_save();
+
+    // The active slot is needed to load the data
+    app.init_persistent::<SaveSlots>();
+    let data = app.world().resource::<SaveSlots>().load_data();
+
+    app.insert_persistent(data)
+        .add_systems(Update, track_play_time.run_if(in_state(GameState::Play)))
+        .add_systems(OnExit(GameState::Play), save_data);
+}
+
+// Resources
+// ---
+
+/// Manages the save slots.
+///
+/// # Examples
+///
+/// ```
+/// use game::prelude::*;
+///
+/// fn new_game(mut slots: ResMut<SaveSlots>, mut data: ResMut<SaveData>) -> Result<()> {
+///     slots.create("second")?;
+///     slots.select("second", &mut data)?;
+///     Ok(())
+/// }
+///
+/// fn list_slots() {
+///     for (name, info) in SaveSlots::list() {
+///         info!("{}: {} ({:.0}s played)", name, info.level, info.play_time);
+///     }
+/// }
+/// ```
+#[derive(Reflect, Resource, Serialize, Deserialize, Persistent)]
+pub struct SaveSlots {
+    active: String,
+}
+
+impl Default for SaveSlots {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_SLOT.into(),
+        }
+    }
+}
+
+impl SaveSlots {
+    /// Returns the name of the active slot.
+    pub fn active(&self) -> &str {
+        &self.active
+    }
+
+    /// Lists every saved slot and its information, starting with the most
+    /// recently played.
+    pub fn list() -> Vec<(String, SlotInfo)> {
+        let extension = format!(".{}", SaveData::FORMAT.extension());
+        let mut slots: Vec<_> = list_keys(SLOTS_PATH)
+            .into_iter()
+            .filter_map(|file| {
+                let name = file.strip_suffix(&extension)?.to_string();
+                let data = read_key(&slot_key(&name))?;
+                match SaveData::decode(&data) {
+                    Ok(data) => Some((name, data.info)),
+                    Err(e) => {
+                        warn!("Couldn't read the save slot '{}': {:?}", name, e);
+                        None
+                    },
+                }
+            })
+            .collect();
+        slots.sort_by_key(|(_, info)| std::cmp::Reverse(info.timestamp));
+        slots
+    }
+
+    /// Checks if a slot has been saved. Invalid names never exist.
+    pub fn exists(name: &str) -> bool {
+        validate_name(name).is_ok() && read_key(&slot_key(name)).is_some()
+    }
+
+    /// Creates a new slot with the default data.
+    pub fn create(&self, name: &str) -> Result<()> {
+        validate_name(name)?;
+        if Self::exists(name) {
+            anyhow::bail!("The slot '{}' already exists", name);
+        }
+        let data = SaveData {
+            info: SlotInfo {
+                timestamp: now(),
+                ..default()
+            },
+            ..default()
+        };
+        write_key(&slot_key(name), &data.encode()?, SaveData::BACKUPS)
+            .with_context(|| format!("Failed to create the slot '{}'", name))
+    }
+
+    /// Copies the saved data of a slot to a new one.
+    /// Changes that have not been persisted yet are not copied.
+    pub fn copy(&self, from: &str, to: &str) -> Result<()> {
+        validate_name(from)?;
+        validate_name(to)?;
+        if Self::exists(to) {
+            anyhow::bail!("The slot '{}' already exists", to);
+        }
+        let data =
+            read_key(&slot_key(from)).with_context(|| format!("The slot '{}' is empty", from))?;
+        write_key(&slot_key(to), &data, SaveData::BACKUPS)
+            .with_context(|| format!("Failed to copy the slot '{}' to '{}'", from, to))
+    }
+
+    /// Deletes a slot. The active slot can't be deleted.
+    pub fn delete(&self, name: &str) -> Result<()> {
+        validate_name(name)?;
+        if name == self.active {
+            anyhow::bail!("The active slot '{}' can't be deleted", name);
+        }
+        remove_key(&slot_key(name)).with_context(|| format!("Failed to delete the slot '{}'", name))
+    }
+
+    /// Saves the data of the active slot, then makes another slot active and
+    /// reads its data. If the slot doesn't exist, the data is reset and it
+    /// will be created the next time it is persisted.
+    pub fn select(&mut self, name: &str, data: &mut SaveData) -> Result<()> {
+        validate_name(name)?;
+        data.persist()
+            .with_context(|| format!("Failed to save the slot '{}'", self.active))?;
+        self.active = name.into();
+        data.slot = name.into();
+        data.reload();
+        self.persist()
+    }
+
+    /// Reads the data of the active slot.
+    fn load_data(&self) -> SaveData {
+        let mut data = SaveData {
+            slot: self.active.clone(),
+            ..default()
+        };
+        data.reload();
+        data
+    }
+}
+
+/// Information about a save slot, useful to show when choosing which one to
+/// load.
+#[derive(Clone, Debug, Default, Reflect, Serialize, Deserialize)]
+pub struct SlotInfo {
+    /// When the slot was last played, in seconds since the unix epoch.
+    pub timestamp: u64,
+    /// The total time spent playing in this slot, in seconds.
+    pub play_time: f64,
+    /// The path of a screenshot of the game to use as the thumbnail.
+    pub thumbnail: Option<String>,
+    /// The name of the current level.
+    pub level: String,
+}
+
+// Systems
+// ---
+
+/// Adds the time spent playing to the active slot.
+fn track_play_time(time: Res<Time>, mut data: ResMut<SaveData>) {
+    data.info.play_time += time.delta_secs_f64();
+    data.info.timestamp = now();
+}
+
+/// Saves the play time when the game stops, since it changes every frame.
+fn save_data(data: Res<SaveData>) {
+    if let Err(e) = data.persist() {
+        error!("{:?}", e);
+    }
+}
+
+// Helpers
+// ---
+
+/// Returns the key where a slot is saved. An empty name is the default slot.
+pub(super) fn slot_key(name: &str) -> String {
+    let name = match name.is_empty() {
+        true => DEFAULT_SLOT,
+        false => name,
+    };
+    format!("{}/{}.{}", SLOTS_PATH, name, SaveData::FORMAT.extension())
+}
+
+/// Slot names are used as file names, so they can't contain paths.
+fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        anyhow::bail!("Invalid slot name '{}'", name);
+    }
+    Ok(())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+/// Before slots existed, `SaveData` was saved on its own. If there are no
+/// slots yet, that data is moved to the default slot.
+fn move_legacy_save() {
+    let legacy = format!("{}.toml", <SaveData as Persistent>::path());
+    if !list_keys(SLOTS_PATH).is_empty() {
+        return;
+    }
+    let Some(data) = read_key(&legacy) else { return };
+    match write_key(&slot_key(DEFAULT_SLOT), &data, SaveData::BACKUPS)
+        .and_then(|_| remove_key(&legacy))
+    {
+        Ok(_) => info!("Moved the saved data to the '{}' slot", DEFAULT_SLOT),
+        Err(e) => warn!("Couldn't move the saved data to a slot: {:?}", e),
+    }
+}
@@ -19,6 +19,7 @@ pub(super) fn plugin(app: &mut App) {
 pub enum GameState {
     /// The game starts on the `Startup` state.
     /// It runs before *anything*, including the `Startup` schedule.
+    /// It ends when every asset collection is loaded (see `LoadingState`).
     #[default]
     Startup,
     /// The main menu of the game. All of the game systems are paused.
@@ -28,4 +29,7 @@ pub enum GameState {
     /// End of the `Play` state.
     /// It can be used to restart the game or handle win/lose conditions.
     End,
+    /// Something went wrong and the game can't continue, for example, some
+    /// assets failed to load. An error screen is shown instead.
+    Error,
 }
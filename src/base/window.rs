@@ -0,0 +1,198 @@
+//! Restores the primary window as it was when the game was last closed, and
+//! saves its state in `GameOptions` when it changes.
+
+use bevy::window::{Monitor, MonitorSelection, PrimaryWindow, WindowMode, WindowPosition};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::Startup), init)
+        .add_systems(Update, save_window_state);
+}
+
+// Resources
+// ---
+
+/// How the window is shown on the monitor.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum DisplayMode {
+    /// A regular window with decorations.
+    #[default]
+    Windowed,
+    /// A window without decorations that covers the whole monitor.
+    Borderless,
+    /// Exclusive fullscreen, changing the video mode of the monitor.
+    Fullscreen,
+}
+
+impl DisplayMode {
+    /// Returns the bevy window mode on a certain monitor.
+    pub fn window_mode(&self, monitor: MonitorSelection) -> WindowMode {
+        match self {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::Borderless => WindowMode::BorderlessFullscreen(monitor),
+            DisplayMode::Fullscreen => WindowMode::Fullscreen(monitor),
+        }
+    }
+}
+
+impl From<WindowMode> for DisplayMode {
+    fn from(mode: WindowMode) -> Self {
+        match mode {
+            WindowMode::Windowed => DisplayMode::Windowed,
+            WindowMode::BorderlessFullscreen(_) => DisplayMode::Borderless,
+            WindowMode::SizedFullscreen(_) | WindowMode::Fullscreen(_) => DisplayMode::Fullscreen,
+        }
+    }
+}
+
+// Systems
+// ---
+
+/// Applies the saved window options.
+/// If the saved monitor is no longer connected, or the window would end up
+/// outside of every monitor, it is centered on the primary monitor instead.
+fn init(
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+    monitors: Query<(Entity, &Monitor)>,
+    options: Res<GameOptions>,
+) {
+    let mut window = single_mut!(window);
+    let res = options.resolution.as_vec2();
+    window.resolution.set(res.x, res.y);
+    window
+        .resolution
+        .set_scale_factor_override(options.scale_factor);
+    window.resizable = options.resizable;
+    window.present_mode = options.present_mode;
+
+    let monitor = options.monitor.as_ref().and_then(|name| {
+        monitors
+            .iter()
+            .find(|(_, monitor)| monitor.name.as_ref() == Some(name))
+    });
+    if options.monitor.is_some() && monitor.is_none() && !monitors.is_empty() {
+        warn!(
+            "The monitor {:?} is not connected, using the primary one",
+            options.monitor
+        );
+    }
+    let selection = match monitor {
+        Some((entity, _)) => MonitorSelection::Entity(entity),
+        None => MonitorSelection::Primary,
+    };
+
+    window.mode = options.mode.window_mode(selection);
+    // Before the monitors are known, the saved position can't be checked
+    let width = window.resolution.physical_width();
+    window.position = match options.position {
+        Some(position)
+            if monitors.is_empty()
+                || monitors
+                    .iter()
+                    .any(|(_, monitor)| is_visible(position, width, monitor)) =>
+        {
+            WindowPosition::At(position)
+        },
+        _ => WindowPosition::Centered(selection),
+    };
+}
+
+/// When the window changes, updates the saved options.
+fn save_window_state(
+    window: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
+    monitors: Query<&Monitor>,
+    mut options: ResMut<GameOptions>,
+) {
+    let window = single!(window);
+    let mode = DisplayMode::from(window.mode);
+
+    // The size of fullscreen windows is the size of the monitor, so only the
+    // windowed size is kept
+    let resolution = match mode {
+        DisplayMode::Windowed => UVec2::new(window.width() as u32, window.height() as u32),
+        _ => options.resolution,
+    };
+    let (position, monitor) = match window.position {
+        WindowPosition::At(position) => {
+            let width = window.resolution.physical_width();
+            let monitor = monitors
+                .iter()
+                .find(|monitor| is_visible(position, width, monitor))
+                .and_then(|monitor| monitor.name.clone());
+            (Some(position), monitor.or(options.monitor.clone()))
+        },
+        _ => (options.position, options.monitor.clone()),
+    };
+    let present_mode = window.present_mode;
+    let scale_factor = window.resolution.scale_factor_override();
+
+    let current = (
+        options.resolution,
+        options.position,
+        &options.monitor,
+        options.mode,
+        options.present_mode,
+        options.scale_factor,
+    );
+    let new = (
+        resolution,
+        position,
+        &monitor,
+        mode,
+        present_mode,
+        scale_factor,
+    );
+    if current == new {
+        return;
+    }
+
+    let _ = options.update(|options| {
+        options.resolution = resolution;
+        options.position = position;
+        options.monitor = monitor.clone();
+        options.mode = mode;
+        options.present_mode = present_mode;
+        options.scale_factor = scale_factor;
+    });
+}
+
+// Helpers
+// ---
+
+/// Checks that the title bar of a window at `position`, with a physical
+/// `width`, is inside of a monitor so it can still be dragged.
+fn is_visible(position: IVec2, width: u32, monitor: &Monitor) -> bool {
+    let title_bar = position + IVec2::new(width as i32 / 2, 8);
+    let min = monitor.physical_position;
+    let max = min + monitor.physical_size().as_ivec2();
+    title_bar.cmpge(min).all() && title_bar.cmplt(max).all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(x: i32, y: i32) -> Monitor {
+        Monitor {
+            name: Some("test".into()),
+            physical_height: 1080,
+            physical_width: 1920,
+            physical_position: IVec2::new(x, y),
+            refresh_rate_millihertz: None,
+            scale_factor: 1.,
+            video_modes: vec![],
+        }
+    }
+
+    #[test]
+    fn windows_outside_of_the_monitors_are_not_visible() {
+        let monitor = monitor(1920, 0);
+        assert!(is_visible(IVec2::new(2000, 100), 600, &monitor));
+        assert!(is_visible(IVec2::new(1700, 0), 600, &monitor));
+        assert!(!is_visible(IVec2::new(100, 100), 600, &monitor));
+        assert!(!is_visible(IVec2::new(2000, 1200), 600, &monitor));
+        assert!(!is_visible(IVec2::new(2000, -50), 600, &monitor));
+    }
+}
@@ -2,14 +2,33 @@
 
 use crate::prelude::*;
 
+pub mod audio;
 pub mod camera;
+pub mod error;
+pub mod loading;
 pub mod music;
+pub mod sfx;
+pub mod spatial;
 
 pub(super) fn plugin(app: &mut App) {
-    app.add_plugins((camera::plugin, music::plugin));
+    app.add_plugins((
+        audio::plugin,
+        camera::plugin,
+        error::plugin,
+        loading::plugin,
+        music::plugin,
+        sfx::plugin,
+        spatial::plugin,
+    ));
 }
 
 /// The prelude for this module.
 pub mod prelude {
-    pub use super::camera::{FinalCamera, GameCamera};
+    pub use super::{
+        audio::{AudioBus, VolumeOptions, VolumeScale},
+        camera::{FinalCamera, GameCamera},
+        music::MusicPlayer,
+        sfx::{Sfx, SfxCommandExt, SfxLimit, SfxLimits},
+        spatial::{SpatialAudio, SpatialAudioExt},
+    };
 }
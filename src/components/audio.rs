@@ -0,0 +1,169 @@
+//! Volume control for every sound in the game.
+//! Audio players can be tagged with an `AudioBus`, and their volume is
+//! multiplied by the volume of that bus and the master volume set in
+//! `GameOptions`.
+
+use bevy::{
+    audio::{AudioSinkPlayback, Volume},
+    transform::TransformSystem,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+pub(super) fn plugin(app: &mut App) {
+    // Sinks are created in `PostUpdate` after the transforms are propagated,
+    // so new players get their volume before, and existing sinks afterwards
+    app.register_type::<(AudioBus, VolumeScale)>()
+        .add_systems(
+            PostUpdate,
+            initial_volume.before(TransformSystem::TransformPropagate),
+        )
+        .add_systems(
+            Last,
+            (apply_volume::<AudioSink>, apply_volume::<SpatialAudioSink>),
+        );
+}
+
+// Components
+// ---
+
+/// The category of an audio player, used to change its volume.
+/// Players without it only follow the master volume.
+///
+/// # Examples
+///
+/// ```
+/// use game::prelude::*;
+///
+/// fn play(mut cmd: Commands, sounds: Res<AssetMap<SoundAssetKey>>) {
+///     cmd.spawn((
+///         AudioPlayer(sounds.get(&SoundAssetKey::Boing)),
+///         PlaybackSettings::DESPAWN,
+///         AudioBus::Sfx,
+///     ));
+/// }
+/// ```
+#[derive(Component, Reflect, Std!)]
+pub enum AudioBus {
+    /// Background music.
+    Music,
+    /// Sound effects of the game.
+    Sfx,
+    /// Sounds of the interface, like clicking buttons.
+    Ui,
+}
+
+/// Multiplies the volume of an audio player, for example to fade it in or out.
+/// Unlike `PlaybackSettings::volume`, it can be changed while it is playing.
+#[derive(Component, Debug, Reflect, Copy!)]
+pub struct VolumeScale(pub f32);
+
+impl Default for VolumeScale {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+/// The `PlaybackSettings::volume` of an audio player before applying its bus
+/// and scale to it.
+#[derive(Component, Debug)]
+struct BaseVolume(f32);
+
+// Resources
+// ---
+
+/// The volume of each bus, from 0 to 1. It is saved in `GameOptions`.
+#[derive(Clone, Debug, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct VolumeOptions {
+    /// Applies to every sound.
+    pub master: f32,
+    /// Applies to `AudioBus::Music`.
+    pub music: f32,
+    /// Applies to `AudioBus::Sfx`.
+    pub sfx: f32,
+    /// Applies to `AudioBus::Ui`.
+    pub ui: f32,
+}
+
+impl Default for VolumeOptions {
+    fn default() -> Self {
+        Self {
+            master: 1.,
+            music: 0.8,
+            sfx: 1.,
+            ui: 1.,
+        }
+    }
+}
+
+impl VolumeOptions {
+    /// Returns the final volume of a bus, including the master volume.
+    pub fn get(&self, bus: Option<&AudioBus>) -> f32 {
+        let volume = match bus {
+            Some(AudioBus::Music) => self.music,
+            Some(AudioBus::Sfx) => self.sfx,
+            Some(AudioBus::Ui) => self.ui,
+            None => 1.,
+        };
+        (self.master * volume).max(0.)
+    }
+}
+
+// Systems
+// ---
+
+type VolumeQuery<'a, T> = (
+    &'a T,
+    &'a PlaybackSettings,
+    Option<&'a BaseVolume>,
+    Option<&'a AudioBus>,
+    Option<&'a VolumeScale>,
+);
+
+/// Scales the volume of audio players before their sinks are created, so they
+/// don't play at full volume until `apply_volume` runs.
+fn initial_volume(
+    mut cmd: Commands,
+    mut players: Query<
+        (
+            Entity,
+            &mut PlaybackSettings,
+            Option<&AudioBus>,
+            Option<&VolumeScale>,
+        ),
+        (
+            Without<BaseVolume>,
+            Without<AudioSink>,
+            Without<SpatialAudioSink>,
+        ),
+    >,
+    options: Res<GameOptions>,
+) {
+    for (entity, mut settings, bus, scale) in &mut players {
+        let base = settings.volume.get();
+        let scale = scale.map_or(1., |scale| scale.0.max(0.));
+        settings.volume = Volume::new(base * options.volume.get(bus) * scale);
+        cmd.entity(entity).insert(BaseVolume(base));
+    }
+}
+
+/// Sets the volume of new or rescaled audio sinks, and of all of them when the
+/// options change.
+fn apply_volume<T: Component + AudioSinkPlayback>(
+    all: Query<VolumeQuery<T>>,
+    added: Query<VolumeQuery<T>, Or<(Added<T>, Changed<VolumeScale>)>>,
+    options: Res<GameOptions>,
+    global: Res<GlobalVolume>,
+) {
+    let set_volume = |(sink, settings, base, bus, scale): VolumeQuery<T>| {
+        let scale = scale.map_or(1., |scale| scale.0.max(0.));
+        let base = base.map_or(settings.volume.get(), |base| base.0);
+        let volume = base * global.volume.get() * options.volume.get(bus);
+        sink.set_volume(volume * scale);
+    };
+    match options.is_changed() {
+        true => all.iter().for_each(set_volume),
+        false => added.iter().for_each(set_volume),
+    }
+}
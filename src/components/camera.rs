@@ -23,7 +23,8 @@ pub struct FinalCamera;
 // ---
 
 /// Spawn the main cameras.
-fn init(mut cmd: Commands, options: Res<GameOptions>) {
+/// The game camera is also where positional sounds are heard from.
+fn init(mut cmd: Commands, options: Res<GameOptions>, spatial: Res<SpatialAudio>) {
     cmd.spawn((
         Camera2d,
         Camera {
@@ -32,5 +33,6 @@ fn init(mut cmd: Commands, options: Res<GameOptions>) {
         },
         GameCamera,
         FinalCamera,
+        spatial.listener(),
     ));
 }
@@ -0,0 +1,46 @@
+//! Error screen shown when the game can't continue.
+
+use bevy::ui::Val::*;
+
+use crate::prelude::*;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::Error), init);
+}
+
+// Systems
+// ---
+
+/// Covers the screen with a list of the errors that stopped the game.
+fn init(mut cmd: Commands, options: Res<GameOptions>, errors: Option<Res<AssetErrors>>) {
+    cmd.spawn((
+        Node {
+            width: Percent(100.),
+            height: Percent(100.),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            row_gap: Px(10.),
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        BackgroundColor(options.palette.darker),
+        GlobalZIndex(1),
+        Name::new("Error Screen"),
+        StateScoped(GameState::Error),
+    ))
+    .with_children(|root| {
+        root.spawn((
+            Text::new("Something went wrong :("),
+            TextFont::from_font_size(32.),
+            TextColor(options.palette.light),
+        ));
+        for error in errors.iter().flat_map(|e| e.iter()) {
+            root.spawn((
+                Text::new(error),
+                TextFont::from_font_size(16.),
+                TextColor(options.palette.primary),
+            ));
+        }
+    });
+}
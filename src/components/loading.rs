@@ -0,0 +1,66 @@
+//! Loading screen shown while the asset collections are loading.
+
+use bevy::ui::Val::*;
+
+use crate::prelude::*;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(LoadingState::Loading), init)
+        .add_systems(Update, update_bar.run_if(in_state(LoadingState::Loading)));
+}
+
+// Components
+// ---
+
+/// Marker for the inner node of the loading bar, its width grows with the
+/// loading progress.
+#[derive(Component)]
+struct LoadingBar;
+
+// Systems
+// ---
+
+/// Spawns a progress bar in the center of the screen.
+fn init(mut cmd: Commands, options: Res<GameOptions>) {
+    cmd.spawn((
+        Node {
+            width: Percent(100.),
+            height: Percent(100.),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        Name::new("Loading Screen"),
+        StateScoped(LoadingState::Loading),
+    ))
+    .with_children(|root| {
+        root.spawn((
+            Node {
+                width: Px(300.),
+                height: Px(24.),
+                border: UiRect::all(Px(3.)),
+                ..default()
+            },
+            BackgroundColor(options.palette.dark),
+            BorderColor(options.palette.light),
+        ))
+        .with_children(|bar| {
+            bar.spawn((
+                Node {
+                    width: Percent(0.),
+                    height: Percent(100.),
+                    ..default()
+                },
+                BackgroundColor(options.palette.primary),
+                LoadingBar,
+            ));
+        });
+    });
+}
+
+/// Resizes the loading bar to match the overall `LoadingProgress`.
+fn update_bar(progress: Res<LoadingProgress>, mut bar: Query<&mut Node, With<LoadingBar>>) {
+    let mut node = single_mut!(bar);
+    node.width = Percent(progress.total().fraction() * 100.);
+}
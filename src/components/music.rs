@@ -1,24 +1,220 @@
 //! Background music for the game.
+//! Each `GameState` can have its own playlist, and `MusicPlayer` crossfades
+//! between tracks when it changes. Tracks that are faded out are paused
+//! instead of stopped, so they continue where they were if they play again.
+//! Only the most recently paused ones are kept, the rest are despawned.
+
+use std::time::Duration;
 
 use bevy::audio::PlaybackMode;
 
 use crate::prelude::*;
 
+/// How many paused tracks are kept to resume them later.
+const MAX_PAUSED_TRACKS: usize = 2;
+
 pub(super) fn plugin(app: &mut App) {
-    app.add_systems(OnEnter(GameState::Play), init);
+    app.init_resource::<MusicPlayer>().add_systems(
+        Update,
+        (
+            change_playlist.run_if(state_changed::<GameState>),
+            update_tracks,
+        )
+            .chain(),
+    );
+}
+
+// Resources
+// ---
+
+/// Controls which music is playing.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use game::prelude::*;
+///
+/// fn setup_music(mut music: ResMut<MusicPlayer>) {
+///     music.fade = Duration::from_secs(3);
+///     music.set_playlist(GameState::End, vec![]);
+/// }
+///
+/// fn boss_fight(mut music: ResMut<MusicPlayer>) {
+///     music.play(MusicAssetKey::Ambient);
+/// }
+/// ```
+#[derive(Resource, Debug)]
+pub struct MusicPlayer {
+    /// How long it takes to crossfade from one track to another.
+    pub fade: Duration,
+    playlists: HashMap<GameState, Vec<MusicAssetKey>>,
+    playlist: Vec<MusicAssetKey>,
+    current: Option<MusicAssetKey>,
+}
+
+impl Default for MusicPlayer {
+    fn default() -> Self {
+        Self {
+            fade: Duration::from_secs(2),
+            playlists: HashMap::from_iter([
+                (GameState::Menu, vec![MusicAssetKey::Ambient]),
+                (GameState::Play, vec![MusicAssetKey::Ambient]),
+            ]),
+            playlist: vec![],
+            current: None,
+        }
+    }
 }
 
-/// Plays background music when the game starts playing and stops when it is
-/// paused.
-fn init(mut cmd: Commands, music_assets: Res<AssetMap<MusicAssetKey>>) {
+impl MusicPlayer {
+    /// Sets the tracks that play in a state, one after the other. If it is
+    /// empty, the music fades out. States without a playlist keep playing
+    /// the previous one.
+    pub fn set_playlist(&mut self, state: GameState, tracks: Vec<MusicAssetKey>) {
+        self.playlists.insert(state, tracks);
+    }
+
+    /// Removes the playlist of a state.
+    pub fn clear_playlist(&mut self, state: GameState) {
+        self.playlists.remove(&state);
+    }
+
+    /// Crossfades to a track and loops it until the state changes.
+    pub fn play(&mut self, track: MusicAssetKey) {
+        self.playlist = vec![track];
+        self.current = Some(track);
+    }
+
+    /// Fades out the music until the state changes.
+    pub fn stop(&mut self) {
+        self.playlist.clear();
+        self.current = None;
+    }
+
+    /// Returns the track that is playing or fading in.
+    pub fn current(&self) -> Option<MusicAssetKey> {
+        self.current
+    }
+
+    /// Returns the track after the current one in the playlist.
+    fn next(&self) -> Option<MusicAssetKey> {
+        let i = self
+            .current
+            .and_then(|track| self.playlist.iter().position(|t| *t == track))
+            .map_or(0, |i| i + 1);
+        self.playlist.get(i % self.playlist.len().max(1)).copied()
+    }
+}
+
+// Components
+// ---
+
+/// A music track that is managed by `MusicPlayer`.
+#[derive(Component, Debug)]
+struct MusicTrack {
+    key: MusicAssetKey,
+    /// When the track was paused after fading out.
+    paused: Option<Duration>,
+}
+
+// Systems
+// ---
+
+/// Switches to the playlist of the new state. If the current track is also
+/// in it, it keeps playing.
+fn change_playlist(state: Res<State<GameState>>, mut music: ResMut<MusicPlayer>) {
+    let Some(playlist) = music.playlists.get(state.get()).cloned() else { return };
+    music.playlist = playlist;
+    if !music
+        .current
+        .is_some_and(|track| music.playlist.contains(&track))
+    {
+        music.current = music.playlist.first().copied();
+    }
+}
+
+/// Fades tracks in and out, spawning them when needed, and moves to the next
+/// track of the playlist when one ends.
+fn update_tracks(
+    mut cmd: Commands,
+    mut tracks: Query<(
+        Entity,
+        &mut MusicTrack,
+        &mut VolumeScale,
+        Option<&AudioSink>,
+    )>,
+    mut music: ResMut<MusicPlayer>,
+    music_assets: Res<AssetMap<MusicAssetKey>>,
+    time: Res<Time<Real>>,
+) {
+    let step = match music.fade.is_zero() {
+        true => 1.,
+        false => time.delta_secs() / music.fade.as_secs_f32(),
+    };
+
+    for (entity, mut track, mut scale, sink) in &mut tracks {
+        // The track ended, so it will be played from the start next time
+        if sink.is_some_and(|sink| sink.empty()) {
+            cmd.entity(entity).despawn_recursive();
+            if music.current == Some(track.key) {
+                music.current = music.next();
+            }
+            continue;
+        }
+
+        let target = if music.current == Some(track.key) { 1. } else { 0. };
+        if scale.0 != target {
+            scale.0 = match target > scale.0 {
+                true => (scale.0 + step).min(target),
+                false => (scale.0 - step).max(target),
+            };
+        }
+
+        match sink {
+            Some(sink) if scale.0 <= 0. && !sink.is_paused() => {
+                sink.pause();
+                track.paused = Some(time.elapsed());
+            },
+            Some(sink) if scale.0 > 0. && sink.is_paused() => {
+                sink.play();
+                track.paused = None;
+            },
+            // The track faded out before it started
+            None if scale.0 <= 0. => cmd.entity(entity).despawn_recursive(),
+            _ => {},
+        }
+    }
+
+    // Older paused tracks are despawned so they don't keep their sinks alive
+    let mut paused: Vec<_> = tracks
+        .iter()
+        .filter_map(|(entity, track, ..)| Some((track.paused?, entity)))
+        .collect();
+    paused.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+    for (_, entity) in paused.into_iter().skip(MAX_PAUSED_TRACKS) {
+        cmd.entity(entity).despawn_recursive();
+    }
+
+    let Some(current) = music.current else { return };
+    if tracks.iter().any(|(_, track, ..)| track.key == current) {
+        return;
+    }
+    // Only a playlist with more than one track needs to know when they end
+    let mode = match music.playlist.len() {
+        0 | 1 => PlaybackMode::Loop,
+        _ => PlaybackMode::Once,
+    };
     cmd.spawn((
-        AudioPlayer(music_assets.get(&MusicAssetKey::Ambient)),
-        PlaybackSettings {
-            mode: PlaybackMode::Loop,
-            paused: true,
-            ..default()
+        Name::new(format!("Music {:?}", current)),
+        MusicTrack {
+            key: current,
+            paused: None,
         },
-        // Here more complex logic might be useful to preserve play state and allow crossfades
-        StateScoped(GameState::Play),
+        AudioPlayer(music_assets.get(&current)),
+        PlaybackSettings { mode, ..default() },
+        AudioBus::Music,
+        VolumeScale(0.),
     ));
 }
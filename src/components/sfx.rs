@@ -0,0 +1,319 @@
+//! Plays sound effects while limiting how many of each can overlap.
+//! Every sound has a maximum number of voices and a cooldown. When all of its
+//! voices are busy, a new sound replaces the one with the lowest priority, or
+//! it is skipped if they are all more important.
+
+use std::time::Duration;
+
+use bevy::audio::Volume;
+
+use crate::prelude::*;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<SfxLimits>()
+        .init_resource::<SfxVoices>();
+}
+
+// Resources
+// ---
+
+/// Limits how a sound effect can be played.
+#[derive(Clone, Debug)]
+pub struct SfxLimit {
+    /// How many copies of the sound can play at the same time.
+    pub max_voices: usize,
+    /// The minimum time between two copies of the sound, in seconds.
+    pub cooldown: f32,
+    /// The priority of the sound, unless another one is set with
+    /// `Sfx::priority`.
+    pub priority: i32,
+}
+
+impl Default for SfxLimit {
+    fn default() -> Self {
+        Self {
+            max_voices: 4,
+            cooldown: 0.05,
+            priority: 0,
+        }
+    }
+}
+
+/// The limits of each sound effect.
+///
+/// # Examples
+///
+/// ```
+/// use game::prelude::*;
+///
+/// fn setup_sounds(mut limits: ResMut<SfxLimits>) {
+///     limits.set(SoundAssetKey::Boing, SfxLimit {
+///         max_voices: 2,
+///         cooldown: 0.1,
+///         ..default()
+///     });
+/// }
+/// ```
+#[derive(Resource, Debug, Default)]
+pub struct SfxLimits {
+    /// Used for sounds without their own limit.
+    pub default: SfxLimit,
+    limits: HashMap<SoundAssetKey, SfxLimit>,
+}
+
+impl SfxLimits {
+    /// Returns the limit of a sound.
+    pub fn get(&self, key: &SoundAssetKey) -> &SfxLimit {
+        self.limits.get(key).unwrap_or(&self.default)
+    }
+
+    /// Changes the limit of a sound.
+    pub fn set(&mut self, key: SoundAssetKey, limit: SfxLimit) {
+        self.limits.insert(key, limit);
+    }
+}
+
+/// Keeps track of the voices of every sound effect.
+#[derive(Resource, Debug, Default)]
+struct SfxVoices {
+    voices: HashMap<SoundAssetKey, Vec<Voice>>,
+    last_played: HashMap<SoundAssetKey, Duration>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Voice {
+    entity: Entity,
+    priority: i32,
+    started: Duration,
+}
+
+// Helpers
+// ---
+
+/// A sound effect to play.
+/// It can be created from a `SoundAssetKey` directly, and it uses the settings
+/// of `SfxLimits` unless they are overriden.
+#[derive(Clone, Debug)]
+pub struct Sfx {
+    key: SoundAssetKey,
+    position: Option<Vec2>,
+    priority: Option<i32>,
+    volume: f32,
+}
+
+impl Sfx {
+    /// Creates a sound effect that is not positional.
+    pub fn new(key: SoundAssetKey) -> Self {
+        Self {
+            key,
+            position: None,
+            priority: None,
+            volume: 1.,
+        }
+    }
+
+    /// Plays the sound at a position in the world (see `SpatialAudio`).
+    pub fn at(mut self, position: Vec2) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Overrides the priority of this voice.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Changes the volume of this voice.
+    pub fn volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+}
+
+impl From<SoundAssetKey> for Sfx {
+    fn from(key: SoundAssetKey) -> Self {
+        Self::new(key)
+    }
+}
+
+/// Convenience function that allows to call `cmd.play_sfx(...)`.
+///
+/// # Examples
+///
+/// ```
+/// use game::prelude::*;
+///
+/// fn bounce(mut cmd: Commands) {
+///     cmd.play_sfx(SoundAssetKey::Boing);
+///     cmd.play_sfx(
+///         Sfx::new(SoundAssetKey::Boing)
+///             .at(Vec2::X * 100.)
+///             .priority(1),
+///     );
+/// }
+/// ```
+pub trait SfxCommandExt {
+    /// Plays a sound effect if its limits allow it.
+    fn play_sfx(&mut self, sfx: impl Into<Sfx>);
+}
+
+impl SfxCommandExt for Commands<'_, '_> {
+    fn play_sfx(&mut self, sfx: impl Into<Sfx>) {
+        let sfx = sfx.into();
+        self.queue(move |world: &mut World| play_sfx(world, sfx));
+    }
+}
+
+/// Plays a sound effect, replacing another voice if there are too many.
+fn play_sfx(world: &mut World, sfx: Sfx) {
+    let now = world.resource::<Time<Real>>().elapsed();
+    let limit = world.resource::<SfxLimits>().get(&sfx.key).clone();
+    let priority = sfx.priority.unwrap_or(limit.priority);
+
+    // Voices are despawned automatically when they end
+    let state = world.resource::<SfxVoices>();
+    let last_played = state.last_played.get(&sfx.key).copied();
+    let mut voices: Vec<_> = state
+        .voices
+        .get(&sfx.key)
+        .into_iter()
+        .flatten()
+        .filter(|voice| world.get_entity(voice.entity).is_ok())
+        .copied()
+        .collect();
+
+    match choose_voice(&mut voices, &limit, priority, now, last_played) {
+        Decision::Play => {},
+        Decision::Steal(voice) => world.entity_mut(voice.entity).despawn_recursive(),
+        Decision::Skip => {
+            world
+                .resource_mut::<SfxVoices>()
+                .voices
+                .insert(sfx.key, voices);
+            return;
+        },
+    }
+
+    let handle = world
+        .resource::<AssetMap<SoundAssetKey>>()
+        .pick(&sfx.key, &mut rand::thread_rng());
+    let mut entity = world.spawn((
+        Name::new(format!("Sfx {:?}", sfx.key)),
+        AudioPlayer(handle),
+        AudioBus::Sfx,
+    ));
+    match sfx.position {
+        Some(position) => {
+            let settings = entity
+                .world()
+                .resource::<SpatialAudio>()
+                .settings(sfx.volume);
+            entity.insert((settings, Transform::from_translation(position.extend(0.))))
+        },
+        None => entity.insert(PlaybackSettings::DESPAWN.with_volume(Volume::new(sfx.volume))),
+    };
+    voices.push(Voice {
+        entity: entity.id(),
+        priority,
+        started: now,
+    });
+
+    let mut state = world.resource_mut::<SfxVoices>();
+    state.voices.insert(sfx.key, voices);
+    state.last_played.insert(sfx.key, now);
+}
+
+/// What to do with a new voice.
+#[derive(Debug, PartialEq)]
+enum Decision {
+    /// There is a free voice.
+    Play,
+    /// Replace an existing voice, which is removed from the list.
+    Steal(Voice),
+    /// The sound is in cooldown or every voice is more important.
+    Skip,
+}
+
+/// Decides if a new voice with `priority` can play given the active `voices`.
+/// The voice to replace is the one with the lowest priority, and the oldest
+/// one if there is a tie.
+fn choose_voice(
+    voices: &mut Vec<Voice>,
+    limit: &SfxLimit,
+    priority: i32,
+    now: Duration,
+    last_played: Option<Duration>,
+) -> Decision {
+    if limit.max_voices == 0 {
+        return Decision::Skip;
+    }
+    if last_played.is_some_and(|last| now.saturating_sub(last).as_secs_f32() < limit.cooldown) {
+        return Decision::Skip;
+    }
+    if voices.len() < limit.max_voices {
+        return Decision::Play;
+    }
+
+    let Some((i, weakest)) = voices
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, voice)| (voice.priority, voice.started))
+    else {
+        return Decision::Play;
+    };
+    if weakest.priority > priority {
+        return Decision::Skip;
+    }
+    Decision::Steal(voices.remove(i))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voice(index: u32, priority: i32, started: u64) -> Voice {
+        Voice {
+            entity: Entity::from_raw(index),
+            priority,
+            started: Duration::from_secs(started),
+        }
+    }
+
+    fn limit(max_voices: usize, cooldown: f32) -> SfxLimit {
+        SfxLimit {
+            max_voices,
+            cooldown,
+            priority: 0,
+        }
+    }
+
+    #[test]
+    fn limits_the_voices() {
+        let now = Duration::from_secs(10);
+        let mut voices = vec![voice(0, 0, 1)];
+        let decision = choose_voice(&mut voices, &limit(2, 0.), 0, now, None);
+        assert_eq!(decision, Decision::Play);
+
+        // The oldest voice with the lowest priority is replaced
+        let mut voices = vec![voice(0, 1, 1), voice(1, 0, 3), voice(2, 0, 2)];
+        let decision = choose_voice(&mut voices, &limit(3, 0.), 0, now, None);
+        assert_eq!(decision, Decision::Steal(voice(2, 0, 2)));
+        assert_eq!(voices.len(), 2);
+
+        // Voices with a higher priority are never replaced
+        let mut voices = vec![voice(0, 2, 1)];
+        let decision = choose_voice(&mut voices, &limit(1, 0.), 1, now, None);
+        assert_eq!(decision, Decision::Skip);
+    }
+
+    #[test]
+    fn respects_the_cooldown() {
+        let last = Some(Duration::from_millis(1000));
+        let limit = limit(4, 0.5);
+        let decision = choose_voice(&mut vec![], &limit, 0, Duration::from_millis(1200), last);
+        assert_eq!(decision, Decision::Skip);
+        let decision = choose_voice(&mut vec![], &limit, 0, Duration::from_millis(1600), last);
+        assert_eq!(decision, Decision::Play);
+    }
+}
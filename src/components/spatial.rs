@@ -0,0 +1,87 @@
+//! Positional audio for 2D games.
+//! The `GameCamera` has a `SpatialListener`, so sounds played with
+//! `SpatialAudioExt::play_sound_at` pan to the side where they happen and get
+//! quieter as they get farther from the center of the screen.
+
+use bevy::{
+    audio::{SpatialScale, Volume},
+    ecs::system::EntityCommands,
+};
+
+use crate::prelude::*;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<SpatialAudio>();
+}
+
+// Resources
+// ---
+
+/// Configures how sounds are heard depending on their position.
+#[derive(Resource, Debug)]
+pub struct SpatialAudio {
+    /// Sounds closer than this distance to the camera, in world units, play
+    /// at full volume. Farther away, they fade with the square of the
+    /// distance. It is also the distance between the ears of the listener.
+    pub distance: f32,
+}
+
+impl Default for SpatialAudio {
+    fn default() -> Self {
+        Self { distance: 400. }
+    }
+}
+
+impl SpatialAudio {
+    /// Returns the listener that is added to the camera.
+    pub fn listener(&self) -> SpatialListener {
+        SpatialListener::new(self.distance)
+    }
+
+    /// Returns the playback settings of a positional sound.
+    pub fn settings(&self, volume: f32) -> PlaybackSettings {
+        PlaybackSettings::DESPAWN
+            .with_volume(Volume::new(volume))
+            .with_spatial(true)
+            .with_spatial_scale(SpatialScale::new_2d(1. / self.distance))
+    }
+}
+
+// Helpers
+// ---
+
+/// Allows to call `cmd.play_sound_at(...)`.
+///
+/// # Examples
+///
+/// ```
+/// use game::prelude::*;
+///
+/// fn explode(mut cmd: Commands) {
+///     cmd.play_sound_at(SoundAssetKey::Boing, Vec2::new(200., 0.));
+/// }
+/// ```
+pub trait SpatialAudioExt {
+    /// Plays a sound effect at a position in the world. If the key has
+    /// several variations, a random one is used.
+    fn play_sound_at(&mut self, key: SoundAssetKey, position: Vec2) -> EntityCommands<'_>;
+}
+
+impl SpatialAudioExt for Commands<'_, '_> {
+    fn play_sound_at(&mut self, key: SoundAssetKey, position: Vec2) -> EntityCommands<'_> {
+        let mut entity = self.spawn((
+            Name::new(format!("Sound {:?}", key)),
+            Transform::from_translation(position.extend(0.)),
+            AudioBus::Sfx,
+        ));
+        entity.queue(move |mut entity: EntityWorldMut| {
+            let world = entity.world();
+            let handle = world
+                .resource::<AssetMap<SoundAssetKey>>()
+                .pick(&key, &mut rand::thread_rng());
+            let settings = world.resource::<SpatialAudio>().settings(1.);
+            entity.insert((AudioPlayer(handle), settings));
+        });
+        entity
+    }
+}
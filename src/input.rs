@@ -1,11 +1,14 @@
 //! This module handles the input using `leafwing_input_manager`.
 
 use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::prelude::*;
 
+pub mod remap;
+
 pub(super) fn plugin(app: &mut App) {
-    app.add_plugins(InputManagerPlugin::<Action>::default())
+    app.add_plugins((InputManagerPlugin::<Action>::default(), remap::plugin))
         .add_systems(OnEnter(GameState::Startup), init);
 }
 
@@ -13,7 +16,10 @@ pub(super) fn plugin(app: &mut App) {
 pub mod prelude {
     pub use leafwing_input_manager::prelude::ActionState;
 
-    pub use super::Action;
+    pub use super::{
+        remap::{Binding, InputBindings, InputCapture, RebindEvent},
+        Action,
+    };
 }
 
 /// These are all the possible game actions that have an input mapping.
@@ -29,7 +35,7 @@ pub mod prelude {
 ///     }
 /// }
 /// ```
-#[derive(Reflect, Std!)]
+#[derive(Reflect, Serialize, Deserialize, Std!)]
 pub enum Action {
     /// Button press usually assigned to Space or the A button in the gamepad
     Act,
@@ -48,24 +54,7 @@ impl Actionlike for Action {
     }
 }
 
-impl Action {
-    fn default_input_map() -> InputMap<Self> {
-        let mut input_map = InputMap::default();
-        input_map
-            .insert(Action::Act, KeyCode::Space)
-            .insert(Action::Act, KeyCode::Enter)
-            .insert(Action::Act, GamepadButton::South)
-            .insert(Action::Act, MouseButton::Left)
-            .insert(Action::Pause, KeyCode::Escape)
-            .insert(Action::Pause, GamepadButton::Start)
-            .insert_dual_axis(Action::Move, VirtualDPad::wasd())
-            .insert_dual_axis(Action::Move, VirtualDPad::arrow_keys())
-            .insert_dual_axis(Action::Move, GamepadStick::LEFT);
-        input_map
-    }
-}
-
-/// Creates a new input manager
-fn init(mut cmd: Commands) {
-    cmd.spawn(InputManagerBundle::with_map(Action::default_input_map()));
+/// Creates a new input manager with the saved bindings
+fn init(mut cmd: Commands, bindings: Res<InputBindings>) {
+    cmd.spawn(InputManagerBundle::with_map(bindings.input_map()));
 }
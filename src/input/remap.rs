@@ -0,0 +1,356 @@
+//! Allows players to change which inputs trigger each `Action`.
+//! The bindings are saved in `InputBindings` and applied to the `InputMap`
+//! as soon as they change.
+//!
+//! Only button actions can be rebound. Dual axis actions like `Action::Move`
+//! always use their default inputs.
+
+use std::collections::BTreeMap;
+
+use bevy::input::gamepad::Gamepad;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<InputCapture>()
+        .add_event::<RebindEvent>()
+        .add_systems(
+            PreUpdate,
+            capture_input
+                .after(bevy::input::InputSystem)
+                .run_if(|capture: Res<InputCapture>| capture.listening().is_some()),
+        )
+        .add_systems(
+            Update,
+            update_input_map.run_if(resource_changed::<InputBindings>),
+        );
+}
+
+// Resources
+// ---
+
+/// The inputs bound to each button action.
+/// Changes are saved in the background at the end of the frame, so rebinds are
+/// kept after restarting the game.
+///
+/// # Examples
+///
+/// ```
+/// use game::prelude::*;
+///
+/// fn use_j_to_act(mut bindings: ResMut<InputBindings>) {
+///     match bindings.bind(Action::Act, Binding::Key(KeyCode::KeyJ)) {
+///         Ok(_) => info!("Act is now on J"),
+///         Err(other) => warn!("J is already used by {:?}", other),
+///     }
+/// }
+/// ```
+#[derive(Reflect, Resource, Serialize, Deserialize, Persistent)]
+pub struct InputBindings {
+    bindings: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        let mut bindings = BTreeMap::new();
+        bindings.insert(Action::Act, vec![
+            Binding::Key(KeyCode::Space),
+            Binding::Key(KeyCode::Enter),
+            Binding::Gamepad(GamepadButton::South),
+            Binding::Mouse(MouseButton::Left),
+        ]);
+        bindings.insert(Action::Pause, vec![
+            Binding::Key(KeyCode::Escape),
+            Binding::Gamepad(GamepadButton::Start),
+        ]);
+        Self { bindings }
+    }
+}
+
+impl InputBindings {
+    /// Returns the inputs bound to an action.
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the action that uses a binding, if any.
+    pub fn action_for(&self, binding: &Binding) -> Option<Action> {
+        if binding.reserved() {
+            return Some(Action::Move);
+        }
+        self.bindings
+            .iter()
+            .find(|(_, bindings)| bindings.contains(binding))
+            .map(|(action, _)| *action)
+    }
+
+    /// Binds an input to an action, replacing its other inputs of the same
+    /// device. If another action already uses the input, nothing changes and
+    /// that action is returned as the error.
+    pub fn bind(&mut self, action: Action, binding: Binding) -> Result<(), Action> {
+        match self.action_for(&binding) {
+            Some(other) if other != action => Err(other),
+            _ => {
+                self.set(action, binding);
+                Ok(())
+            },
+        }
+    }
+
+    /// Binds an input to an action, removing it from any other action that
+    /// used it. Inputs reserved by dual axis actions can't be rebound.
+    pub fn replace(&mut self, action: Action, binding: Binding) -> Result<(), Action> {
+        if binding.reserved() {
+            return Err(Action::Move);
+        }
+        for bindings in self.bindings.values_mut() {
+            bindings.retain(|b| *b != binding);
+        }
+        self.set(action, binding);
+        Ok(())
+    }
+
+    /// Returns the bindings of an action to their defaults. Other actions that
+    /// were using those inputs lose them.
+    pub fn reset_action(&mut self, action: Action) {
+        let defaults = Self::default().get(action).to_vec();
+        for bindings in self.bindings.values_mut() {
+            bindings.retain(|b| !defaults.contains(b));
+        }
+        self.bindings.insert(action, defaults);
+    }
+
+    /// Builds the input map with the current bindings.
+    pub fn input_map(&self) -> InputMap<Action> {
+        let mut input_map = InputMap::default();
+        for (action, bindings) in &self.bindings {
+            for binding in bindings {
+                binding.insert(&mut input_map, *action);
+            }
+        }
+        input_map
+            .insert_dual_axis(Action::Move, VirtualDPad::wasd())
+            .insert_dual_axis(Action::Move, VirtualDPad::arrow_keys())
+            .insert_dual_axis(Action::Move, GamepadStick::LEFT);
+        input_map
+    }
+
+    fn set(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        bindings.retain(|b| !b.same_device(&binding));
+        bindings.push(binding);
+    }
+}
+
+/// Listens for the next input to bind it to an action.
+/// When an input is pressed, a `RebindEvent` is sent with the result.
+///
+/// # Examples
+///
+/// ```
+/// use game::prelude::*;
+///
+/// fn start_rebind(mut capture: ResMut<InputCapture>) {
+///     capture.listen(Action::Act);
+/// }
+///
+/// fn on_rebind(mut events: EventReader<RebindEvent>, mut bindings: ResMut<InputBindings>) {
+///     for event in events.read() {
+///         // Ask the player if they want to move the input from the other action
+///         if let RebindEvent::Conflict {
+///             action, binding, ..
+///         } = event
+///         {
+///             let _ = bindings.replace(*action, binding.clone());
+///         }
+///     }
+/// }
+/// ```
+#[derive(Resource, Debug, Default)]
+pub struct InputCapture {
+    listening: Option<Action>,
+}
+
+impl InputCapture {
+    /// Binds the next input that is pressed to `action`.
+    pub fn listen(&mut self, action: Action) {
+        self.listening = Some(action);
+    }
+
+    /// Stops listening without changing anything.
+    pub fn cancel(&mut self) {
+        self.listening = None;
+    }
+
+    /// Returns the action that is waiting for an input.
+    pub fn listening(&self) -> Option<Action> {
+        self.listening
+    }
+}
+
+// Events
+// ---
+
+/// The result of listening for an input with `InputCapture`.
+#[derive(Event, Debug, Clone)]
+pub enum RebindEvent {
+    /// The input was bound to the action.
+    Rebound {
+        /// The action that was rebound.
+        action: Action,
+        /// The new input.
+        binding: Binding,
+    },
+    /// The input is already used by another action, so nothing changed.
+    /// `InputBindings::replace` can be used to bind it anyway.
+    Conflict {
+        /// The action that was being rebound.
+        action: Action,
+        /// The input that was pressed.
+        binding: Binding,
+        /// The action that already uses the input.
+        other: Action,
+    },
+}
+
+// Helpers
+// ---
+
+/// An input that can be bound to a button action.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum Binding {
+    /// A key of the keyboard.
+    Key(KeyCode),
+    /// A mouse button.
+    Mouse(MouseButton),
+    /// A button of any gamepad.
+    Gamepad(GamepadButton),
+}
+
+impl Binding {
+    fn insert(&self, input_map: &mut InputMap<Action>, action: Action) {
+        match self {
+            Binding::Key(key) => input_map.insert(action, *key),
+            Binding::Mouse(button) => input_map.insert(action, *button),
+            Binding::Gamepad(button) => input_map.insert(action, *button),
+        };
+    }
+
+    fn same_device(&self, other: &Binding) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    /// Inputs used by `Action::Move`, which can't be rebound.
+    fn reserved(&self) -> bool {
+        use KeyCode::*;
+        matches!(
+            self,
+            Binding::Key(KeyW | KeyA | KeyS | KeyD | ArrowUp | ArrowDown | ArrowLeft | ArrowRight)
+        )
+    }
+}
+
+// Systems
+// ---
+
+/// Waits for the first input that is pressed and binds it.
+fn capture_input(
+    mut capture: ResMut<InputCapture>,
+    mut bindings: ResMut<InputBindings>,
+    mut events: EventWriter<RebindEvent>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
+) {
+    let Some(action) = capture.listening else { return };
+    let Some(binding) = keys
+        .get_just_pressed()
+        .map(|key| Binding::Key(*key))
+        .chain(mouse.get_just_pressed().map(|b| Binding::Mouse(*b)))
+        .chain(
+            gamepads
+                .iter()
+                .flat_map(|gamepad| gamepad.get_just_pressed())
+                .map(|b| Binding::Gamepad(*b)),
+        )
+        .next()
+    else {
+        return;
+    };
+
+    capture.listening = None;
+    events.send(match bindings.bind(action, binding.clone()) {
+        Ok(_) => RebindEvent::Rebound { action, binding },
+        Err(other) => RebindEvent::Conflict {
+            action,
+            binding,
+            other,
+        },
+    });
+}
+
+/// Applies the bindings to the input map when they change.
+fn update_input_map(bindings: Res<InputBindings>, mut input_maps: Query<&mut InputMap<Action>>) {
+    for mut input_map in &mut input_maps {
+        *input_map = bindings.input_map();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_conflicts() {
+        let mut bindings = InputBindings::default();
+        let space = Binding::Key(KeyCode::Space);
+        assert_eq!(
+            bindings.bind(Action::Pause, space.clone()),
+            Err(Action::Act)
+        );
+        assert_eq!(
+            bindings.bind(Action::Act, Binding::Key(KeyCode::KeyW)),
+            Err(Action::Move)
+        );
+
+        // Replacing moves the input to the new action
+        assert!(bindings.replace(Action::Pause, space.clone()).is_ok());
+        assert_eq!(bindings.action_for(&space), Some(Action::Pause));
+        assert!(!bindings
+            .get(Action::Pause)
+            .contains(&Binding::Key(KeyCode::Escape)));
+        assert!(!bindings.get(Action::Act).contains(&space));
+    }
+
+    #[test]
+    fn rebinds_and_resets() {
+        let mut bindings = InputBindings::default();
+        let j = Binding::Key(KeyCode::KeyJ);
+        assert!(bindings.bind(Action::Act, j.clone()).is_ok());
+        // Only the inputs of the same device are replaced
+        assert_eq!(bindings.get(Action::Act), &[
+            Binding::Gamepad(GamepadButton::South),
+            Binding::Mouse(MouseButton::Left),
+            j.clone(),
+        ]);
+
+        bindings.reset_action(Action::Act);
+        assert_eq!(
+            bindings.get(Action::Act),
+            InputBindings::default().get(Action::Act)
+        );
+    }
+
+    #[test]
+    fn saves_the_bindings() {
+        let mut bindings = InputBindings::default();
+        bindings
+            .bind(Action::Pause, Binding::Mouse(MouseButton::Other(4)))
+            .unwrap();
+        let data = bindings.encode().unwrap();
+        let saved = InputBindings::decode(&data).unwrap();
+        assert_eq!(saved.bindings, bindings.bindings);
+    }
+}
@@ -28,27 +28,31 @@ pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        // The embedded plugin, if enabled, must come before bevy's `AssetPlugin`
-        // #[cfg(feature = "embedded")]
-        // app.add_plugins(assets::embedded::plugin);
-
         // Default bevy plugins
-        let asset_plugin = AssetPlugin {
-            meta_check: bevy::asset::AssetMetaCheck::Never,
-            ..default()
-        };
+        // They are skipped if they were already added, for example, to create a
+        // headless app for testing
+        if !app.is_plugin_added::<AssetPlugin>() {
+            // The asset layers (mods, embedded assets and the asset folder) must
+            // come before bevy's `AssetPlugin`
+            app.add_plugins(assets::layers::plugin);
+
+            let asset_plugin = AssetPlugin {
+                meta_check: bevy::asset::AssetMetaCheck::Never,
+                ..default()
+            };
 
-        let window_plugin = WindowPlugin {
-            primary_window: Some(Window {
-                title: "Hello Bevy".into(),
-                canvas: Some("#bevy".into()),
-                prevent_default_event_handling: false,
+            let window_plugin = WindowPlugin {
+                primary_window: Some(Window {
+                    title: "Hello Bevy".into(),
+                    canvas: Some("#bevy".into()),
+                    prevent_default_event_handling: false,
+                    ..default()
+                }),
                 ..default()
-            }),
-            ..default()
-        };
+            };
 
-        app.add_plugins(DefaultPlugins.set(asset_plugin).set(window_plugin));
+            app.add_plugins(DefaultPlugins.set(asset_plugin).set(window_plugin));
+        }
 
         // Game plugins
         app.add_plugins((
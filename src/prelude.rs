@@ -8,6 +8,9 @@ pub use bevy::{
     utils::HashMap,
     window::{PrimaryWindow, WindowResized},
 };
+// Used by `#[derive(Persistent)]`
+#[doc(hidden)]
+pub use inventory;
 pub use macros::*;
 
 pub use crate::{
@@ -1,7 +1,7 @@
 //! Mappings menu screen.
 
 // TODO: Show keymaps
-// TODO: Remapping
+// TODO: Remapping (see the `rebind` example for `InputCapture`)
 
 use crate::prelude::*;
 
@@ -0,0 +1,101 @@
+//! Builds several game apps in the same process to check that they don't share
+//! any state.
+
+use std::time::Duration;
+
+use bevy::{
+    log::LogPlugin,
+    render::{settings::WgpuSettings, RenderPlugin},
+    window::ExitCondition,
+    winit::WinitPlugin,
+};
+use common::data_dir;
+use game::prelude::*;
+use serde::{Deserialize, Serialize};
+
+mod common;
+
+/// Creates a game app without a window or a renderer.
+/// Call `data_dir` before, so that it doesn't save in the user data directory.
+fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            })
+            .set(RenderPlugin {
+                render_creation: WgpuSettings {
+                    backends: None,
+                    ..default()
+                }
+                .into(),
+                ..default()
+            })
+            .disable::<WinitPlugin>()
+            .disable::<LogPlugin>(),
+        GamePlugin,
+    ));
+    app.finish();
+    app.cleanup();
+    app
+}
+
+/// Updates the app until it reaches `GameState::Play` or it runs out of tries.
+fn update_until_play(app: &mut App) -> bool {
+    for _ in 0..500 {
+        app.update();
+        if *app.world().resource::<State<GameState>>() == GameState::Play {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+#[test]
+fn pending_assets_are_per_app() {
+    let _dir = data_dir();
+    let mut first = headless_app();
+    let mut second = headless_app();
+
+    assert!(update_until_play(&mut first));
+
+    // The second app must still track its own collections even if the first one
+    // finished loading
+    second.update();
+    let progress = second.world().resource::<LoadingProgress>();
+    assert!(progress.get::<FontAssetKey>().is_some());
+    assert!(progress.get::<MetaAssetKey>().is_some());
+    assert!(progress.get::<MusicAssetKey>().is_some());
+    assert!(progress.get::<SoundAssetKey>().is_some());
+}
+
+#[test]
+fn apps_load_in_parallel() {
+    let _dir = data_dir();
+    let handles: Vec<_> = (0..2)
+        .map(|_| std::thread::spawn(|| update_until_play(&mut headless_app())))
+        .collect();
+
+    for handle in handles {
+        assert!(handle.join().expect("The app thread panicked"));
+    }
+}
+
+/// A resource that is added to the app because it derives `Persistent`.
+#[derive(Reflect, Resource, Serialize, Deserialize, Default, Persistent)]
+struct Registered {
+    value: u32,
+}
+
+#[test]
+fn adds_persistent_resources() {
+    let _dir = data_dir();
+    let app = headless_app();
+    assert!(app.world().contains_resource::<Registered>());
+    assert!(app.world().contains_resource::<GameOptions>());
+    assert!(app.world().contains_resource::<InputBindings>());
+}
@@ -0,0 +1,44 @@
+//! Helpers shared by the integration tests.
+
+use std::{
+    ops::Deref,
+    path::Path,
+    sync::{Mutex, Once, PoisonError},
+};
+
+/// How many tests are using the temporary save directory.
+static USERS: Mutex<usize> = Mutex::new(0);
+
+/// Gives access to the save directory of the tests, which is removed when the
+/// last test that is using it finishes.
+pub struct DataDir(&'static Path);
+
+impl Deref for DataDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        self.0
+    }
+}
+
+impl Drop for DataDir {
+    fn drop(&mut self) {
+        let mut users = USERS.lock().unwrap_or_else(PoisonError::into_inner);
+        *users -= 1;
+        if *users == 0 {
+            let _ = std::fs::remove_dir_all(self.0);
+        }
+    }
+}
+
+/// Saves the data of the tests in a temporary directory instead of the user
+/// data directory. It must be called before anything is loaded or saved.
+pub fn data_dir() -> DataDir {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        let dir = std::env::temp_dir().join(format!("game-test-{}", std::process::id()));
+        game::base::data::set_data_dir(dir).expect("The save directory was set before the tests");
+    });
+    *USERS.lock().unwrap_or_else(PoisonError::into_inner) += 1;
+    DataDir(game::base::data::data_dir())
+}
@@ -0,0 +1,371 @@
+//! Loads data saved with older formats to check that the migrations of
+//! `Persistent` resources keep working.
+
+use std::time::Duration;
+
+use common::data_dir;
+use game::prelude::*;
+use serde::{Deserialize, Serialize};
+
+mod common;
+
+/// Updates the app until the condition is met or it runs out of tries.
+fn update_until(app: &mut App, condition: impl Fn() -> bool) -> bool {
+    for _ in 0..100 {
+        app.update();
+        if condition() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+/// A resource that went through a few format changes.
+/// - Version 1 renamed `score` to `points`.
+/// - Version 2 added `level`, which starts at 1.
+#[derive(Reflect, Resource, Serialize, Deserialize, Default, Debug, PartialEq, Persistent)]
+#[persistent(version = 2, migrate = migrate_progress)]
+struct Progress {
+    name: String,
+    points: u32,
+    level: u32,
+}
+
+fn migrate_progress(version: u32, mut data: toml::Value) -> Result<toml::Value> {
+    let table = data.as_table_mut().context("Not a table")?;
+    match version {
+        0 => {
+            let score = table.remove("score").context("Missing score")?;
+            table.insert("points".into(), score);
+        },
+        1 => {
+            table.insert("level".into(), 1.into());
+        },
+        _ => anyhow::bail!("Unknown version {}", version),
+    }
+    Ok(data)
+}
+
+fn expected_progress() -> Progress {
+    Progress {
+        name: "eri".into(),
+        points: 42,
+        level: 1,
+    }
+}
+
+#[test]
+fn migrates_from_every_version() {
+    let v0 = Progress::decode(include_str!("fixtures/Progress.v0.toml")).unwrap();
+    assert_eq!(v0, expected_progress());
+
+    let v1 = Progress::decode(include_str!("fixtures/Progress.v1.toml")).unwrap();
+    assert_eq!(v1, expected_progress());
+}
+
+#[test]
+fn saves_the_current_version() {
+    let data = expected_progress().encode().unwrap();
+    assert!(data.contains("_version = 2"));
+    assert_eq!(Progress::decode(&data).unwrap(), expected_progress());
+}
+
+#[test]
+fn rejects_invalid_data() {
+    // Newer versions can't be read
+    let newer = "_version = 3\nname = \"eri\"\npoints = 42\nlevel = 1";
+    assert!(Progress::decode(newer).is_err());
+
+    // Broken migrations are reported instead of resetting the data
+    assert!(Progress::decode("name = \"eri\"").is_err());
+    assert!(Progress::decode("not toml").is_err());
+}
+
+#[test]
+fn migrates_game_options() {
+    let options = GameOptions::decode(include_str!("fixtures/GameOptions.v0.toml")).unwrap();
+    assert!(options.resizable);
+    assert_eq!(options.resolution, UVec2::new(800, 600));
+    assert!(options.mods.is_empty());
+    assert_eq!(options.mode, DisplayMode::Windowed);
+    assert!(options.position.is_none());
+    assert_eq!(options.volume, VolumeOptions::default());
+}
+
+#[test]
+fn migrates_save_data() {
+    let data = SaveData::decode(include_str!("fixtures/SaveData.v0.toml")).unwrap();
+    assert!(data.test);
+    assert_eq!(data.info.play_time, 0.);
+    assert!(data.info.thumbnail.is_none());
+}
+
+/// A resource that is saved to disk to test the backups.
+#[derive(Reflect, Resource, Serialize, Deserialize, Default, Debug, Persistent)]
+struct Backups {
+    value: u32,
+}
+
+#[test]
+fn restores_from_backups() {
+    let dir = data_dir();
+    let mut data = Backups::default();
+    for value in 1..=5 {
+        data.update(|data| data.value = value).unwrap();
+    }
+
+    // A corrupted save falls back to the newest backup
+    std::fs::write(dir.join("Backups.toml"), "value = ").unwrap();
+    data.reload();
+    assert_eq!(data.value, 4);
+
+    // Only the newest backups are kept
+    assert!(dir.join("backups/Backups.3.toml").exists());
+    assert!(!dir.join("backups/Backups.4.toml").exists());
+}
+
+/// A resource that is saved as soon as it changes.
+#[derive(Reflect, Resource, Serialize, Deserialize, Default, Debug, Persistent)]
+struct Immediate {
+    value: u32,
+}
+
+/// A resource that is saved in the background.
+#[derive(Reflect, Resource, Serialize, Deserialize, Default, Debug, Persistent)]
+#[persistent(policy = SavePolicy::Debounced(0.))]
+struct Debounced {
+    value: u32,
+}
+
+/// A resource that is only saved when the app exits.
+#[derive(Reflect, Resource, Serialize, Deserialize, Default, Debug, Persistent)]
+#[persistent(policy = SavePolicy::OnExit)]
+struct OnExit {
+    value: u32,
+}
+
+#[test]
+fn follows_the_save_policy() {
+    let dir = data_dir();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .init_persistent::<Immediate>()
+        .init_persistent::<Debounced>()
+        .init_persistent::<OnExit>();
+    app.update();
+
+    // Changing immediate data directly saves it in the background at the end
+    // of the frame
+    app.world_mut().resource_mut::<Immediate>().value = 1;
+    assert!(update_until(&mut app, || Immediate::load().value == 1));
+
+    // Updating doesn't save right away
+    app.world_mut()
+        .resource_mut::<Debounced>()
+        .update(|data| data.value = 1)
+        .unwrap();
+    app.world_mut().resource_mut::<OnExit>().value = 1;
+    assert!(!dir.join("Debounced.toml").exists());
+
+    // Debounced data is saved in the background after the delay
+    assert!(update_until(&mut app, || Debounced::load().value == 1));
+    assert_eq!(OnExit::load().value, 0);
+
+    // The rest is saved when exiting
+    app.world_mut().send_event(AppExit::Success);
+    app.update();
+    assert_eq!(OnExit::load().value, 1);
+}
+
+/// A resource that is saved as ron in a custom file.
+#[derive(Reflect, Resource, Serialize, Deserialize, Default, Debug, PartialEq, Persistent)]
+#[persistent(path = "custom", format = Format::Ron)]
+struct Ron {
+    name: String,
+    values: Vec<u32>,
+}
+
+/// A resource that is saved as json.
+#[derive(Reflect, Resource, Serialize, Deserialize, Default, Debug, PartialEq, Persistent)]
+#[persistent(format = Format::Json, version = 1, migrate = migrate_json)]
+struct Json {
+    value: u32,
+}
+
+fn migrate_json(_: u32, mut data: toml::Value) -> Result<toml::Value> {
+    let table = data.as_table_mut().context("Not a table")?;
+    table.insert("value".into(), 7.into());
+    Ok(data)
+}
+
+/// A resource that is saved in a binary format.
+#[derive(Reflect, Resource, Serialize, Deserialize, Default, Debug, PartialEq, Persistent)]
+#[persistent(format = Format::Binary)]
+struct Binary {
+    name: String,
+    scale: f32,
+    levels: Vec<Ron>,
+    unlocked: Option<bool>,
+}
+
+#[test]
+fn saves_in_every_format() {
+    let dir = data_dir();
+    let ron = Ron {
+        name: "eri".into(),
+        values: vec![1, 2, 3],
+    };
+    ron.persist().unwrap();
+    assert!(dir.join("custom.ron").exists());
+    assert_eq!(Ron::load(), ron);
+
+    let json = Json { value: 3 };
+    json.persist().unwrap();
+    let saved = std::fs::read_to_string(dir.join("Json.json")).unwrap();
+    assert!(saved.contains("\"_version\": 1"));
+    assert_eq!(Json::load(), json);
+
+    // Migrations work the same way as with toml
+    assert_eq!(Json::decode("{}").unwrap(), Json { value: 7 });
+
+    let binary = Binary {
+        name: "eri".into(),
+        scale: 1.5,
+        levels: vec![ron],
+        unlocked: Some(true),
+    };
+    binary.persist().unwrap();
+    let saved = std::fs::read_to_string(dir.join("Binary.bin")).unwrap();
+    assert!(!saved.contains("eri"));
+    assert_eq!(Binary::load(), binary);
+}
+
+/// A resource that can't be represented as toml, so it can't be saved in any
+/// format.
+#[derive(Reflect, Resource, Serialize, Deserialize, Default, Persistent)]
+#[persistent(format = Format::Json, register = false)]
+struct Unsupported {
+    values: Vec<Option<u32>>,
+}
+
+#[test]
+fn rejects_unsupported_data() {
+    let dir = data_dir();
+    let unsupported = Unsupported {
+        values: vec![Some(1), None],
+    };
+    assert!(unsupported.persist().is_err());
+    assert!(!dir.join("Unsupported.json").exists());
+
+    // Values that toml supports still work
+    let supported = Unsupported {
+        values: vec![Some(1), Some(2)],
+    };
+    supported.persist().unwrap();
+    assert_eq!(Unsupported::load().values, supported.values);
+}
+
+/// A resource whose saves are signed.
+#[derive(Reflect, Resource, Serialize, Deserialize, Default, Debug, PartialEq, Persistent)]
+#[persistent(protection = Protection::Signed)]
+struct Signed {
+    score: u32,
+}
+
+/// A resource whose saves are encrypted.
+#[derive(Reflect, Resource, Serialize, Deserialize, Default, Debug, PartialEq, Persistent)]
+#[persistent(protection = Protection::Encrypted, format = Format::Json)]
+struct Encrypted {
+    score: u32,
+}
+
+#[test]
+fn rejects_tampered_data() {
+    let signed = Signed { score: 10 }.encode().unwrap();
+    assert!(signed.contains("score = 10"));
+    assert_eq!(Signed::decode(&signed).unwrap(), Signed { score: 10 });
+    assert!(Signed::decode(&signed.replace("score = 10", "score = 9999")).is_err());
+    assert!(Signed::decode("score = 10").is_err());
+
+    let encrypted = Encrypted { score: 10 }.encode().unwrap();
+    assert!(!encrypted.contains("score"));
+    assert_eq!(Encrypted::decode(&encrypted).unwrap(), Encrypted {
+        score: 10
+    });
+    let mut tampered = encrypted.into_bytes();
+    tampered[20] = if tampered[20] == b'A' { b'B' } else { b'A' };
+    assert!(Encrypted::decode(&String::from_utf8(tampered).unwrap()).is_err());
+}
+
+#[test]
+fn restores_tampered_data_from_backups() {
+    let dir = data_dir();
+    let mut data = Signed::default();
+    for score in 1..=2 {
+        data.update(|data| data.score = score).unwrap();
+    }
+
+    let path = dir.join("Signed.toml");
+    let saved = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&path, saved.replace("score = 2", "score = 9999")).unwrap();
+    data.reload();
+    assert_eq!(data.score, 1);
+}
+
+#[test]
+fn saves_the_previous_slot() {
+    let _dir = data_dir();
+    let mut slots = SaveSlots::default();
+    let mut data = SaveData::default();
+    data.info.play_time = 10.;
+
+    // The progress of each slot is kept when switching between them
+    slots.select("other", &mut data).unwrap();
+    assert_eq!(data.info.play_time, 0.);
+    slots.select("default", &mut data).unwrap();
+    assert_eq!(data.info.play_time, 10.);
+    assert_eq!(slots.active(), "default");
+}
+
+#[test]
+fn saves_rebound_inputs() {
+    let _dir = data_dir();
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .init_persistent::<InputBindings>();
+    app.update();
+
+    let j = Binding::Key(KeyCode::KeyJ);
+    app.world_mut()
+        .resource_mut::<InputBindings>()
+        .bind(Action::Act, j.clone())
+        .unwrap();
+
+    // The binding is still there after loading it again
+    assert!(update_until(&mut app, || {
+        InputBindings::load().get(Action::Act).contains(&j)
+    }));
+}
+
+/// Saved next to the slots folder, where a slot name with `..` could reach it.
+#[derive(Reflect, Resource, Serialize, Deserialize, Default, Persistent)]
+#[persistent(register = false)]
+struct Outside {
+    value: u32,
+}
+
+#[test]
+fn rejects_slot_paths() {
+    let dir = data_dir();
+    let slots = SaveSlots::default();
+    let outside = Outside { value: 1 };
+    outside.persist().unwrap();
+
+    // Slot names can't reach files outside of the slots folder
+    assert!(slots.delete("../Outside").is_err());
+    assert!(slots.copy("../Outside", "copy").is_err());
+    assert!(slots.create("../other").is_err());
+    assert!(!SaveSlots::exists("../Outside"));
+    assert!(dir.join(outside.key()).exists());
+}